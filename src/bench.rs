@@ -0,0 +1,467 @@
+//! Timing the solvers on a fixed set of problems with every backend and thread setting, and
+//! comparing the timings and node counts against an earlier run to catch regressions.
+
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use crate::board::{create_board, Branching, SearchResult, Supply};
+use crate::cubes::{create_cuboid, polycube_from_piece, Polycube};
+use crate::error::Error;
+use crate::lattice::{create_lattice_board, hexiamonds, pentahexes, Lattice, Polyform};
+use crate::pieces::{standard_pieces, Piece};
+use crate::transfer::count_strip_tilings;
+
+/// Runs within this many milliseconds of their baseline are never reported as slower, as such
+/// small differences are mostly noise.
+pub const MIN_SLOWDOWN_MILLIS: u128 = 10;
+
+/// A rectangle to fill with some of the 12 pentominoes, or some of the pieces of a lattice,
+/// each used once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchProblem {
+    pub name: &'static str,
+    pub width: usize,
+    pub height: usize,
+    /// The letters of the pieces to use.
+    pub pieces: &'static str,
+    /// The lattice of triangles or hexagons whose pieces fill the board, or None for
+    /// pentominoes on squares.
+    pub lattice: Option<Lattice>,
+}
+
+/// The problems benchmarked by default, small enough for every backend to finish in seconds.
+pub fn bench_problems() -> Vec<BenchProblem> {
+    vec![
+        BenchProblem {
+            name: "5x8",
+            width: 8,
+            height: 5,
+            pieces: "FILNPTUV",
+            lattice: None,
+        },
+        BenchProblem {
+            name: "4x10",
+            width: 10,
+            height: 4,
+            pieces: "FILNPTUV",
+            lattice: None,
+        },
+        BenchProblem {
+            name: "3x15",
+            width: 15,
+            height: 3,
+            pieces: "FILNPTUVY",
+            lattice: None,
+        },
+        BenchProblem {
+            name: "hexiamonds-12x5",
+            width: 12,
+            height: 5,
+            pieces: "ABCDEFGHIJ",
+            lattice: Some(Lattice::Triangular),
+        },
+    ]
+}
+
+/// The ways of solving a problem that can be timed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    PieceOrder,
+    MostConstrainedCell,
+    /// `Board::count_solutions`, counting without listing the solutions.
+    TranspositionTable,
+    /// `count_strip_tilings`, for problems at most six cells across.
+    TransferMatrix,
+    /// `Cuboid`, filling a box one cube deep with flat pentominoes.
+    Cubes,
+    /// `LatticeBoard`, the only backend for problems on triangles or hexagons.
+    Lattice,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 6] = [
+        Backend::PieceOrder,
+        Backend::MostConstrainedCell,
+        Backend::TranspositionTable,
+        Backend::TransferMatrix,
+        Backend::Cubes,
+        Backend::Lattice,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::PieceOrder => "piece-order",
+            Backend::MostConstrainedCell => "most-constrained-cell",
+            Backend::TranspositionTable => "transposition-table",
+            Backend::TransferMatrix => "transfer-matrix",
+            Backend::Cubes => "cubes",
+            Backend::Lattice => "lattice",
+        }
+    }
+
+    /// Whether the backend splits its search over threads, so is timed with each thread limit.
+    pub fn threaded(&self) -> bool {
+        !matches!(self, Backend::TranspositionTable | Backend::TransferMatrix)
+    }
+}
+
+/// The thread limits to time threaded backends with, as for `Board::threads`: one thread, one
+/// per core and one per top-level subtree.
+pub fn thread_settings() -> Vec<usize> {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut settings = vec![1];
+    if cores > 1 {
+        settings.push(cores);
+    }
+    settings.push(0);
+
+    settings
+}
+
+/// One timed run, as written to and read back from a results file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchResult {
+    pub problem: String,
+    pub backend: String,
+    /// The thread limit, or 0 for a thread per top-level subtree.
+    pub threads: usize,
+    pub millis: u128,
+    /// Board states reached, or 0 for backends that don't search.
+    pub nodes: u64,
+    /// Solutions found or counted, including the rotations and reflections the backend doesn't
+    /// rule out.
+    pub solutions: u64,
+}
+
+// Runs a search, returning what it returns and how many milliseconds it took.
+fn timed<T>(search: impl FnOnce() -> T) -> (T, u128) {
+    let start = Instant::now();
+    let result = search();
+
+    (result, start.elapsed().as_millis())
+}
+
+// The node and solution counts of a search that lists its solutions.
+fn listed(result: SearchResult) -> (u64, u64) {
+    (result.statistics.nodes(), result.solutions.len() as u64)
+}
+
+/// Solves a problem with a backend and thread limit, or returns None when the backend can't
+/// solve that problem.
+pub fn run_bench(problem: &BenchProblem, backend: Backend, threads: usize) -> Option<BenchResult> {
+    let ((nodes, solutions), millis) = match (problem.lattice, backend) {
+        (Some(lattice), Backend::Lattice) => {
+            let pieces = match lattice {
+                Lattice::Hexagonal => pentahexes(),
+                Lattice::Triangular => hexiamonds(),
+            };
+            let orientations: Arc<Vec<Vec<Polyform>>> = Arc::new(
+                pieces
+                    .iter()
+                    .filter(|piece| problem.pieces.contains(piece.name))
+                    .map(Polyform::all_orientations)
+                    .collect(),
+            );
+            let supply = vec![Supply::Exactly(1); orientations.len()];
+            let mut board = create_lattice_board(lattice, problem.width, problem.height);
+            board.set_threads(threads);
+            timed(|| listed(board.find_solutions_with_supply(&orientations, &supply)))
+        }
+        (Some(_), _) | (None, Backend::Lattice) => return None,
+        (None, backend) => run_square_bench(problem, backend, threads)?,
+    };
+
+    Some(BenchResult {
+        problem: problem.name.to_string(),
+        backend: backend.name().to_string(),
+        threads,
+        millis,
+        nodes,
+        solutions,
+    })
+}
+
+// Times a pentomino problem, returning its node and solution counts with the milliseconds
+// taken.
+fn run_square_bench(
+    problem: &BenchProblem,
+    backend: Backend,
+    threads: usize,
+) -> Option<((u64, u64), u128)> {
+    let pieces: Vec<Piece> = standard_pieces()
+        .into_iter()
+        .filter(|piece| problem.pieces.contains(piece.name_char()))
+        .collect();
+    let transforms: Arc<Vec<Vec<Piece>>> =
+        Arc::new(pieces.iter().map(Piece::all_transforms).collect());
+    let supply = vec![Supply::Exactly(1); pieces.len()];
+    let mut board = create_board(problem.width, problem.height);
+    board.set_threads(threads);
+
+    match backend {
+        Backend::PieceOrder | Backend::MostConstrainedCell => {
+            let branching = match backend {
+                Backend::PieceOrder => Branching::PieceOrder,
+                _ => Branching::MostConstrainedCell,
+            };
+            Some(timed(|| {
+                listed(board.find_solutions_with_supply(&transforms, &supply, branching))
+            }))
+        }
+        Backend::TranspositionTable => Some(timed(|| {
+            let count = board.count_solutions(&transforms, &supply, 256 << 20);
+            (count.statistics.nodes(), count.count)
+        })),
+        Backend::TransferMatrix => {
+            let height = problem.width.min(problem.height);
+            let length = problem.width.max(problem.height);
+            let (counts, millis) =
+                timed(|| count_strip_tilings(height, length, &transforms, &supply));
+            let solutions = counts.ok()?[length].to_u64().unwrap_or(u64::MAX);
+            Some(((0, solutions), millis))
+        }
+        Backend::Cubes => {
+            let orientations: Arc<Vec<Vec<Polycube>>> = Arc::new(
+                pieces
+                    .iter()
+                    .map(|piece| polycube_from_piece(piece).all_orientations())
+                    .collect(),
+            );
+            let width = u8::try_from(problem.width).ok()?;
+            let height = u8::try_from(problem.height).ok()?;
+            let mut cuboid = create_cuboid(width, height, 1);
+            cuboid.set_threads(threads);
+            Some(timed(|| {
+                listed(cuboid.find_solutions_with_supply(&orientations, &supply))
+            }))
+        }
+        Backend::Lattice => None,
+    }
+}
+
+impl BenchResult {
+    /// Whether both results are for the same problem, backend and thread limit.
+    pub fn same_run(&self, other: &BenchResult) -> bool {
+        (&self.problem, &self.backend, self.threads)
+            == (&other.problem, &other.backend, other.threads)
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.problem, self.backend, self.threads, self.millis, self.nodes, self.solutions
+        )
+    }
+}
+
+/// The header line of a results file, naming the columns.
+pub const BENCH_HEADER: &str = "# problem backend threads ms nodes solutions";
+
+/// Reads results written one per line as by `BenchResult`'s `Display`, skipping blank lines
+/// and comments starting with '#'.
+pub fn parse_bench_results(text: &str) -> Result<Vec<BenchResult>, Error> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let bad_line = || Error::BadBenchResult(line.to_string());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [problem, backend, threads, millis, nodes, solutions] = fields[..] else {
+                return Err(bad_line());
+            };
+
+            Ok(BenchResult {
+                problem: problem.to_string(),
+                backend: backend.to_string(),
+                threads: threads.parse().map_err(|_| bad_line())?,
+                millis: millis.parse().map_err(|_| bad_line())?,
+                nodes: nodes.parse().map_err(|_| bad_line())?,
+                solutions: solutions.parse().map_err(|_| bad_line())?,
+            })
+        })
+        .collect()
+}
+
+/// How a run got worse than its baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Regression {
+    Slower {
+        baseline: u128,
+        millis: u128,
+    },
+    MoreNodes {
+        baseline: u64,
+        nodes: u64,
+    },
+    /// A different number of solutions, which means a bug rather than a slowdown.
+    DifferentSolutions {
+        baseline: u64,
+        solutions: u64,
+    },
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Regression::Slower { baseline, millis } => {
+                write!(f, "slower: {}ms, was {}ms", millis, baseline)
+            }
+            Regression::MoreNodes { baseline, nodes } => {
+                write!(f, "more nodes: {}, was {}", nodes, baseline)
+            }
+            Regression::DifferentSolutions {
+                baseline,
+                solutions,
+            } => write!(f, "different solutions: {}, was {}", solutions, baseline),
+        }
+    }
+}
+
+/// Compares a run with the same run from a baseline, flagging it when it finds different
+/// solutions, reaches more board states or takes more than `tolerance` longer, as a fraction
+/// of the baseline time.
+pub fn regressions(
+    result: &BenchResult,
+    baseline: &BenchResult,
+    tolerance: f64,
+) -> Vec<Regression> {
+    let mut regressions = vec![];
+
+    if result.solutions != baseline.solutions {
+        regressions.push(Regression::DifferentSolutions {
+            baseline: baseline.solutions,
+            solutions: result.solutions,
+        });
+    }
+    if result.nodes > baseline.nodes {
+        regressions.push(Regression::MoreNodes {
+            baseline: baseline.nodes,
+            nodes: result.nodes,
+        });
+    }
+    let allowed = (baseline.millis as f64 * (1.0 + tolerance)) as u128;
+    if result.millis > allowed.max(baseline.millis + MIN_SLOWDOWN_MILLIS) {
+        regressions.push(Regression::Slower {
+            baseline: baseline.millis,
+            millis: result.millis,
+        });
+    }
+
+    regressions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::{
+        parse_bench_results, regressions, run_bench, Backend, BenchProblem, BenchResult,
+        Regression, BENCH_HEADER,
+    };
+    use crate::error::Error;
+    use crate::lattice::Lattice;
+
+    #[test]
+    fn backends_agree_on_a_small_problem() {
+        let problem = BenchProblem {
+            name: "3x5",
+            width: 5,
+            height: 3,
+            pieces: "LPV",
+            lattice: None,
+        };
+
+        let counted = run_bench(&problem, Backend::TranspositionTable, 1).unwrap();
+        let swept = run_bench(&problem, Backend::TransferMatrix, 1).unwrap();
+        assert!(counted.solutions > 0);
+        assert_eq!(counted.solutions, swept.solutions);
+        assert_eq!(0, swept.nodes);
+        for threads in [0, 1] {
+            for backend in [Backend::MostConstrainedCell, Backend::Cubes] {
+                let listed = run_bench(&problem, backend, threads).unwrap();
+                assert_eq!(counted.solutions, listed.solutions);
+                assert!(listed.nodes > 0);
+            }
+        }
+        assert_eq!(None, run_bench(&problem, Backend::Lattice, 1));
+
+        let wide = BenchProblem {
+            width: 7,
+            height: 7,
+            ..problem
+        };
+        assert_eq!(None, run_bench(&wide, Backend::TransferMatrix, 1));
+    }
+
+    #[test]
+    fn lattice_problems_only_run_on_the_lattice_backend() {
+        let problem = BenchProblem {
+            name: "hexiamonds-12x4",
+            width: 12,
+            height: 4,
+            pieces: "EFGHIJKL",
+            lattice: Some(Lattice::Triangular),
+        };
+
+        for threads in [0, 1] {
+            let result = run_bench(&problem, Backend::Lattice, threads).unwrap();
+            assert_eq!(2, result.solutions);
+            assert!(result.nodes > 0);
+        }
+        for backend in [Backend::PieceOrder, Backend::TransferMatrix, Backend::Cubes] {
+            assert_eq!(None, run_bench(&problem, backend, 1));
+        }
+    }
+
+    #[test]
+    fn reads_results_back_and_flags_regressions() {
+        let baseline = BenchResult {
+            problem: "5x8".to_string(),
+            backend: "piece-order".to_string(),
+            threads: 0,
+            millis: 100,
+            nodes: 5000,
+            solutions: 27,
+        };
+        let text = format!("{}\n{}\n", BENCH_HEADER, baseline);
+        assert_eq!(Ok(vec![baseline.clone()]), parse_bench_results(&text));
+        assert_eq!(
+            Err(Error::BadBenchResult("5x8 piece-order".to_string())),
+            parse_bench_results("5x8 piece-order")
+        );
+
+        let slightly_slower = BenchResult {
+            millis: 115,
+            ..baseline.clone()
+        };
+        assert!(slightly_slower.same_run(&baseline));
+        assert!(regressions(&slightly_slower, &baseline, 0.2).is_empty());
+
+        let worse = BenchResult {
+            millis: 130,
+            nodes: 5001,
+            solutions: 26,
+            ..baseline.clone()
+        };
+        assert_eq!(
+            vec![
+                Regression::DifferentSolutions {
+                    baseline: 27,
+                    solutions: 26
+                },
+                Regression::MoreNodes {
+                    baseline: 5000,
+                    nodes: 5001
+                },
+                Regression::Slower {
+                    baseline: 100,
+                    millis: 130
+                },
+            ],
+            regressions(&worse, &baseline, 0.2)
+        );
+    }
+}
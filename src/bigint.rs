@@ -0,0 +1,121 @@
+use std::fmt;
+
+/// An unsigned integer of any size, for counts that outgrow `u64`. Only what counting needs is
+/// supported: adding and printing in decimal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BigUint {
+    // Base 2^32 digits, least significant first, without trailing zeros.
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn add(&mut self, other: &BigUint) {
+        if self.digits.len() < other.digits.len() {
+            self.digits.resize(other.digits.len(), 0);
+        }
+
+        let mut carry = 0u64;
+        for (i, digit) in self.digits.iter_mut().enumerate() {
+            let sum =
+                u64::from(*digit) + u64::from(other.digits.get(i).copied().unwrap_or(0)) + carry;
+            *digit = sum as u32;
+            carry = sum >> 32;
+            if carry == 0 && i >= other.digits.len() {
+                break;
+            }
+        }
+        if carry > 0 {
+            self.digits.push(carry as u32);
+        }
+    }
+
+    /// The value if it fits in a `u64`.
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits[..] {
+            [] => Some(0),
+            [low] => Some(u64::from(low)),
+            [low, high] => Some(u64::from(low) | u64::from(high) << 32),
+            _ => None,
+        }
+    }
+
+    // Divides in place, returning the remainder.
+    fn divide_by(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for digit in self.digits.iter_mut().rev() {
+            let current = remainder << 32 | u64::from(*digit);
+            *digit = (current / u64::from(divisor)) as u32;
+            remainder = current % u64::from(divisor);
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+
+        remainder as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> BigUint {
+        let mut digits = vec![value as u32, (value >> 32) as u32];
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        BigUint { digits }
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Groups of nine decimal digits, least significant first.
+        let mut groups = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            groups.push(rest.divide_by(1_000_000_000));
+        }
+
+        write!(f, "{}", groups.last().unwrap())?;
+        for group in groups.iter().rev().skip(1) {
+            write!(f, "{:09}", group)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bigint::BigUint;
+
+    #[test]
+    fn adds_past_u64() {
+        let mut sum = BigUint::from(u64::MAX);
+        sum.add(&BigUint::from(1));
+
+        assert_eq!(None, sum.to_u64());
+        assert_eq!("18446744073709551616", sum.to_string());
+
+        let mut doubled = sum.clone();
+        doubled.add(&sum);
+        assert_eq!("36893488147419103232", doubled.to_string());
+    }
+
+    #[test]
+    fn prints_small_values() {
+        assert_eq!("0", BigUint::zero().to_string());
+        assert_eq!("1000000000", BigUint::from(1_000_000_000).to_string());
+        assert_eq!(Some(4040), BigUint::from(4040).to_u64());
+    }
+}
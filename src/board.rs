@@ -1,404 +1,2246 @@
+use crate::constraints::Constraints;
+use crate::error::Error;
+use crate::interrupt::{stopped, INTERRUPTED};
 use crate::pieces::Piece;
+use crate::report::{report, Reporter, SearchEvent};
+use crate::stats::SearchStatistics;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::mem::{size_of, size_of_val};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
-#[derive(Clone)]
-pub(crate) struct Placement<'a> {
-    pub(crate) row: u8,
-    pub(crate) column: u8,
-    pub(crate) piece: &'a Piece,
+#[derive(Clone, Debug)]
+pub struct Placement<'a> {
+    pub row: usize,
+    pub column: usize,
+    pub piece: &'a Piece,
 }
 
-#[derive(Clone)]
-pub(crate) struct Board<'a> {
-    pub(crate) width: u8,
-    pub(crate) height: u8,
+/// A board cell a placement would cover that is already covered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    pub row: usize,
+    pub column: usize,
+    /// The index into `Board::placements` of the piece covering the cell, or None when the
+    /// cell was filled without placing a piece.
+    pub placed: Option<usize>,
+    pub placed_name: Option<char>,
+}
+
+/// Everything that keeps a placement off a board, as found by `Board::check_placement`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlacementProblems {
+    /// Squares beyond an edge of the board, at the row and column they would have had on a
+    /// larger board.
+    pub off_board: Vec<(usize, usize)>,
+    pub collisions: Vec<Collision>,
+    /// Squares of a coloured piece on board cells of the other colour.
+    pub colour_mismatches: Vec<(usize, usize)>,
+    pub breaks_constraints: bool,
+}
+
+impl PlacementProblems {
+    pub fn is_empty(&self) -> bool {
+        self.off_board.is_empty()
+            && self.collisions.is_empty()
+            && self.colour_mismatches.is_empty()
+            && !self.breaks_constraints
+    }
+}
+
+impl fmt::Display for PlacementProblems {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut reasons: Vec<String> = self
+            .off_board
+            .iter()
+            .map(|(row, column)| {
+                format!("square at row {} column {} is off the board", row, column)
+            })
+            .collect();
+        for collision in self.collisions.iter() {
+            reasons.push(match collision.placed_name {
+                Some(name) => format!(
+                    "cell at row {} column {} is covered by piece {}",
+                    collision.row, collision.column, name
+                ),
+                None => format!(
+                    "cell at row {} column {} is already covered",
+                    collision.row, collision.column
+                ),
+            });
+        }
+        for (row, column) in self.colour_mismatches.iter() {
+            reasons.push(format!(
+                "square at row {} column {} doesn't match the colour of the board",
+                row, column
+            ));
+        }
+        if self.breaks_constraints {
+            reasons.push("placement breaks the board's constraints".to_string());
+        }
+
+        write!(f, "{}", reasons.join("; "))
+    }
+}
+
+/// How the search chooses what to branch on at each level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Branching {
+    /// Place the pieces in list order, trying every position for each.
+    PieceOrder,
+    /// Pick the empty cell with the fewest legal covering placements and try each of those.
+    MostConstrainedCell,
+}
+
+#[derive(Debug)]
+pub struct SearchResult {
+    pub solutions: Vec<String>,
+    pub statistics: SearchStatistics,
+    /// False when the search was stopped early, leaving only the solutions found by then.
+    pub complete: bool,
+}
+
+/// The number of ways to fill a board, as counted by `Board::count_solutions`.
+#[derive(Debug)]
+pub struct SolutionCount {
+    pub count: u64,
+    pub statistics: SearchStatistics,
+    /// Board states whose count was looked up instead of searched again.
+    pub table_hits: u64,
+    pub table_entries: usize,
+    /// False when the count was stopped early, leaving only the solutions counted by then.
+    pub complete: bool,
+}
+
+// Subtree counts keyed by which cells are filled and what is left to place, which fully decide
+// how many ways the rest of the board can be filled. Once it holds `capacity` entries nothing
+// more is added, keeping memory bounded while the entries near the root stay useful.
+struct TranspositionTable {
+    entries: HashMap<(Vec<u64>, Vec<Supply>), u64>,
+    capacity: usize,
+    hits: u64,
+}
+
+/// Whether a puzzle has exactly one solution, as a logic puzzle built from clues should.
+#[derive(Debug, PartialEq)]
+pub enum Uniqueness {
+    NoSolution,
+    Unique(String),
+    /// The distinct solutions found, in sorted order.
+    Ambiguous(Vec<String>),
+    /// The search was stopped before finding a second solution.
+    Interrupted,
+}
+
+/// How many copies of a piece the search may place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Supply {
+    Exactly(u32),
+    /// Up to this many copies, letting the search pick which pieces to use.
+    AtMost(u32),
+    Unlimited,
+}
+
+impl Supply {
+    pub(crate) fn available(&self) -> bool {
+        !matches!(self, Supply::Exactly(0) | Supply::AtMost(0))
+    }
+
+    pub(crate) fn required(&self) -> bool {
+        matches!(self, Supply::Exactly(count) if *count > 0)
+    }
+
+    pub(crate) fn optional(&self) -> bool {
+        !matches!(self, Supply::Exactly(_))
+    }
+
+    pub(crate) fn take(&mut self) {
+        if let Supply::Exactly(count) | Supply::AtMost(count) = self {
+            *count -= 1;
+        }
+    }
+
+    pub(crate) fn give_back(&mut self) {
+        if let Supply::Exactly(count) | Supply::AtMost(count) = self {
+            *count += 1;
+        }
+    }
+
+    pub(crate) fn allows(&self, count: usize) -> bool {
+        match self {
+            Supply::Exactly(n) => count == *n as usize,
+            Supply::AtMost(n) => count <= *n as usize,
+            Supply::Unlimited => true,
+        }
+    }
+}
+
+// Checks that a solution read back from an outside solver places each piece as often as its
+// supply allows, given the group of every placement it uses.
+pub(crate) fn check_piece_counts(
+    transforms: &[Vec<Piece>],
+    supply: &[Supply],
+    used: impl IntoIterator<Item = usize>,
+) -> Result<(), Error> {
+    let mut counts = vec![0; supply.len()];
+    for group in used {
+        counts[group] += 1;
+    }
+
+    match (0..supply.len()).find(|group| !supply[*group].allows(counts[*group])) {
+        Some(group) => Err(Error::WrongPieceCount {
+            name: transforms[group][0].name.name_char(),
+            count: counts[group],
+            supply: supply[group],
+        }),
+        None => Ok(()),
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Move {
+    piece: usize,
+    transform: usize,
+    row: usize,
+    column: usize,
+}
+
+/// Which edges of a board are joined to the opposite edge, letting pieces wrap around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Topology {
+    Rectangle,
+    /// The left and right edges are joined.
+    Cylinder,
+    /// Both pairs of opposite edges are joined.
+    Torus,
+}
+
+impl Topology {
+    pub fn wraps_rows(self) -> bool {
+        self == Topology::Torus
+    }
+
+    pub fn wraps_columns(self) -> bool {
+        self != Topology::Rectangle
+    }
+}
+
+/// A rectangular board, possibly with holes, coloured cells, joined edges and constraints on
+/// the pieces, along with the pieces placed on it so far.
+#[derive(Clone, Debug)]
+pub struct Board<'a> {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
     pub(crate) placements: Vec<Placement<'a>>,
     pub(crate) filled: Vec<bool>,
+    pub(crate) constraints: Constraints,
+    // Whether each cell is dark, or empty for an uncoloured board. Coloured pieces only fit
+    // where their colours line up with the board's.
+    pub(crate) colours: Vec<bool>,
+    pub(crate) topology: Topology,
+    // Checked as the search goes, which stops early once it is set.
+    pub(crate) stop: &'static AtomicBool,
+    // How many top-level subtrees are searched at once, each on its own thread, or 0 to start
+    // a thread for every one of them.
+    pub(crate) threads: usize,
+    pub(crate) reporter: Option<Reporter>,
+    // Whether the piece order search leaves out first moves whose solutions are reflections or
+    // rotations of others.
+    pub(crate) cut_symmetry: bool,
+}
+
+// Runs each search of a top-level subtree on its own thread, at most `threads` at once unless
+// it is 0, and hands each one's solutions and statistics to `finished`. With a thread limit the
+// oldest search is waited for before starting another, so solutions stay in order.
+pub(crate) fn search_subtrees<F>(
+    subtrees: impl IntoIterator<Item = F>,
+    threads: usize,
+    mut finished: impl FnMut(Vec<String>, SearchStatistics),
+) where
+    F: FnOnce() -> (Vec<String>, SearchStatistics) + Send + 'static,
+{
+    let mut children: VecDeque<thread::JoinHandle<_>> = VecDeque::new();
+    for subtree in subtrees {
+        if threads > 0 && children.len() >= threads {
+            let (solutions, statistics) = children.pop_front().unwrap().join().unwrap();
+            finished(solutions, statistics);
+        }
+        children.push_back(thread::spawn(subtree));
+    }
+
+    for child in children {
+        let (solutions, statistics) = child.join().unwrap();
+        finished(solutions, statistics);
+    }
+}
+
+fn all_reflectable(transforms: &[Vec<Piece>]) -> bool {
+    transforms.iter().all(|t| !t[0].one_sided)
 }
 
-pub(crate) fn create_board<'a>(width: u8, height: u8) -> Board<'a> {
+pub fn create_board<'a>(width: usize, height: usize) -> Board<'a> {
     Board {
         width,
         height,
         placements: vec![],
-        filled: vec![false; usize::from(width * height)],
+        filled: vec![false; width * height],
+        constraints: Constraints::default(),
+        colours: vec![],
+        topology: Topology::Rectangle,
+        stop: &INTERRUPTED,
+        threads: 0,
+        reporter: None,
+        cut_symmetry: true,
+    }
+}
+
+/// A board with the given cells, as (row, column), left out so no piece can cover them.
+pub fn create_board_with_holes<'a>(
+    width: usize,
+    height: usize,
+    holes: &[(usize, usize)],
+) -> Result<Board<'a>, Error> {
+    let mut board = create_board(width, height);
+    for &(row, column) in holes {
+        if row >= height || column >= width {
+            return Err(Error::HoleOffBoard { row, column });
+        }
+        board.filled[row * width + column] = true;
+    }
+
+    Ok(board)
+}
+
+/// A board whose left and right edges are joined.
+pub fn create_cylinder<'a>(width: usize, height: usize) -> Board<'a> {
+    Board {
+        topology: Topology::Cylinder,
+        ..create_board(width, height)
+    }
+}
+
+/// A board whose opposite edges are both joined.
+pub fn create_torus<'a>(width: usize, height: usize) -> Board<'a> {
+    Board {
+        topology: Topology::Torus,
+        ..create_board(width, height)
+    }
+}
+
+// Where a piece has to start along one axis for its square at `offset` to land on `cell`.
+fn origin(cell: usize, offset: u8, length: usize, wraps: bool) -> Option<usize> {
+    let offset = usize::from(offset);
+
+    if wraps {
+        Some((cell + length - offset) % length)
+    } else {
+        cell.checked_sub(offset)
+    }
+}
+
+/// A board coloured like a chessboard, with a dark top left cell.
+pub fn create_checkerboard<'a>(width: usize, height: usize) -> Board<'a> {
+    Board {
+        colours: (0..height)
+            .flat_map(|row| (0..width).map(move |column| (row + column) % 2 == 0))
+            .collect(),
+        ..create_board(width, height)
     }
 }
 
 impl<'a> Board<'a> {
-    pub(crate) fn empty(&self, row: u8, column: u8) -> bool {
-        !self.filled[usize::from(row * self.width + column)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pieces placed so far, in the order they were placed.
+    pub fn placements(&self) -> &[Placement<'a>] {
+        &self.placements
+    }
+
+    pub fn constraints(&self) -> &Constraints {
+        &self.constraints
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+
+    /// Whether each cell is dark, or empty for an uncoloured board.
+    pub fn colours(&self) -> &[bool] {
+        &self.colours
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Makes searches check `stop` instead of the flag set by Ctrl-C.
+    pub fn set_stop_flag(&mut self, stop: &'static AtomicBool) {
+        self.stop = stop;
+    }
+
+    /// Limits how many top-level subtrees are searched at once, each on its own thread. 0, the
+    /// default, starts a thread for every one of them.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    /// Reports each solution and the search's progress as it goes.
+    pub fn set_reporter(&mut self, reporter: Reporter) {
+        self.reporter = Some(reporter);
+    }
+
+    /// Whether the piece order search skips first moves that only lead to reflections and
+    /// rotations of other solutions, which it does by default. Without the cut it lists every
+    /// solution, as the most constrained cell search does.
+    pub fn set_symmetry_cut(&mut self, cut_symmetry: bool) {
+        self.cut_symmetry = cut_symmetry;
+    }
+
+    /// Whether a cell is on the board and not yet covered.
+    pub fn empty(&self, row: usize, column: usize) -> bool {
+        row < self.height && column < self.width && !self.filled[row * self.width + column]
+    }
+    // The board cell under a square of a placed piece, wrapping around joined edges, or None
+    // when it falls off the board. A placement has to start on the board, even where edges wrap.
+    fn cell_index(&self, placement: &Placement, piece_row: u8, piece_column: u8) -> Option<usize> {
+        let (width, height) = (self.width, self.height);
+        if placement.row >= height || placement.column >= width {
+            return None;
+        }
+        let mut row = placement.row + usize::from(piece_row);
+        let mut column = placement.column + usize::from(piece_column);
+
+        if self.topology.wraps_rows() {
+            row %= height;
+        }
+        if self.topology.wraps_columns() {
+            column %= width;
+        }
+
+        (row < height && column < width).then_some(row * width + column)
+    }
+    /// The (row, column) of each board cell covered by a placement.
+    pub fn covered_cells(&self, placement: &Placement) -> Vec<(usize, usize)> {
+        let piece = placement.piece;
+
+        (0..piece.height)
+            .flat_map(|row| (0..piece.width).map(move |column| (row, column)))
+            .filter(|(row, column)| piece.is_solid(*row, *column))
+            .filter_map(|(row, column)| self.cell_index(placement, row, column))
+            .map(|index| (index / self.width, index % self.width))
+            .collect()
+    }
+    /// Whether a cell lies along an edge that isn't joined to another.
+    pub fn on_border(&self, row: usize, column: usize) -> bool {
+        (!self.topology.wraps_rows() && (row == 0 || row == self.height - 1))
+            || (!self.topology.wraps_columns() && (column == 0 || column == self.width - 1))
     }
     fn update_filled(&mut self, placement: &Placement, new_value: bool) {
         for piece_column in 0..placement.piece.width {
             for piece_row in 0..placement.piece.height {
                 if placement.piece.is_solid(piece_row, piece_column) {
-                    self.filled[usize::from(
-                        (piece_row + placement.row) * self.width + piece_column + placement.column,
-                    )] = new_value;
+                    let index = self.cell_index(placement, piece_row, piece_column).unwrap();
+                    self.filled[index] = new_value;
                 }
             }
         }
     }
-    pub(crate) fn try_add(&mut self, placement: Placement<'a>) -> bool {
+    fn can_contain(&self, piece: &Piece) -> bool {
+        usize::from(piece.width) <= self.width && usize::from(piece.height) <= self.height
+    }
+    // Along a joined axis a piece can start anywhere on the board and wrap around the edge.
+    fn in_bounds(&self, placement: &Placement) -> bool {
+        let fits_along = |start: usize, size: u8, length: usize, wraps: bool| {
+            if wraps {
+                start < length
+            } else {
+                start + usize::from(size) <= length
+            }
+        };
+
+        fits_along(
+            placement.row,
+            placement.piece.height,
+            self.height,
+            self.topology.wraps_rows(),
+        ) && fits_along(
+            placement.column,
+            placement.piece.width,
+            self.width,
+            self.topology.wraps_columns(),
+        )
+    }
+    // How many rows and columns a piece can start at.
+    fn positions(&self, piece: &Piece) -> (usize, usize) {
+        let rows = if self.topology.wraps_rows() {
+            self.height
+        } else {
+            1 + self.height - usize::from(piece.height)
+        };
+        let columns = if self.topology.wraps_columns() {
+            self.width
+        } else {
+            1 + self.width - usize::from(piece.width)
+        };
+
+        (rows, columns)
+    }
+    fn fits(&self, placement: &Placement) -> bool {
+        if !self.constraints.is_empty() && !self.constraints.allows(placement, self) {
+            return false;
+        }
+
         for piece_row in 0..placement.piece.height {
             for piece_column in 0..placement.piece.width {
-                if placement.piece.is_solid(piece_row, piece_column)
-                    && !self.empty(piece_row + placement.row, piece_column + placement.column)
-                {
-                    return false;
+                if !placement.piece.is_solid(piece_row, piece_column) {
+                    continue;
+                }
+
+                match self.cell_index(placement, piece_row, piece_column) {
+                    Some(index)
+                        if !self.filled[index]
+                            && self.colours_match(placement, index, piece_row, piece_column) => {}
+                    _ => return false,
                 }
             }
         }
 
+        true
+    }
+    fn colours_match(
+        &self,
+        placement: &Placement,
+        index: usize,
+        piece_row: u8,
+        piece_column: u8,
+    ) -> bool {
+        self.colours.is_empty()
+            || !placement.piece.is_coloured()
+            || self.colours[index] == placement.piece.is_dark(piece_row, piece_column)
+    }
+    pub fn try_add(&mut self, placement: Placement<'a>) -> bool {
+        if !self.fits(&placement) {
+            return false;
+        }
+
         self.update_filled(&placement, true);
         self.placements.push(placement.clone());
         true
     }
 
-    pub(crate) fn remove_last(&mut self) {
-        let removed_placement = self.placements.pop().unwrap();
+    /// Everything that keeps a placement off the board: the squares that hang over an edge, the
+    /// cells already covered and by which piece, squares on the wrong colour and whether the
+    /// constraints forbid it. Empty when `try_add` would accept the placement.
+    pub fn check_placement(&self, placement: &Placement) -> PlacementProblems {
+        let piece = placement.piece;
+        let mut problems = PlacementProblems::default();
+
+        for piece_row in 0..piece.height {
+            for piece_column in 0..piece.width {
+                if !piece.is_solid(piece_row, piece_column) {
+                    continue;
+                }
+
+                let Some(index) = self.cell_index(placement, piece_row, piece_column) else {
+                    problems.off_board.push((
+                        placement.row + usize::from(piece_row),
+                        placement.column + usize::from(piece_column),
+                    ));
+                    continue;
+                };
+                let (row, column) = (index / self.width, index % self.width);
+                if self.filled[index] {
+                    let placed = self
+                        .placements
+                        .iter()
+                        .position(|p| self.covered_cells(p).contains(&(row, column)));
+                    problems.collisions.push(Collision {
+                        row,
+                        column,
+                        placed,
+                        placed_name: placed.map(|p| self.placements[p].piece.name_char()),
+                    });
+                } else if !self.colours_match(placement, index, piece_row, piece_column) {
+                    problems.colour_mismatches.push((row, column));
+                }
+            }
+        }
+        problems.breaks_constraints =
+            !self.constraints.is_empty() && !self.constraints.allows(placement, self);
+
+        problems
+    }
+
+    /// Adds a placement like `try_add`, but says why it can't go there.
+    pub fn try_place(&mut self, placement: Placement<'a>) -> Result<(), Error> {
+        let problems = self.check_placement(&placement);
+        if !problems.is_empty() {
+            return Err(Error::IllegalPlacement(problems));
+        }
+
+        self.update_filled(&placement, true);
+        self.placements.push(placement);
+        Ok(())
+    }
+
+    /// Takes the last piece placed off the board.
+    ///
+    /// # Panics
+    ///
+    /// When there are no pieces on the board.
+    pub fn remove_last(&mut self) {
+        self.try_remove_last()
+            .unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    /// Takes the last piece placed off the board and returns it.
+    pub fn try_remove_last(&mut self) -> Result<Placement<'a>, Error> {
+        let removed_placement = self.placements.pop().ok_or(Error::NothingToRemove)?;
 
         self.update_filled(&removed_placement, false);
+        Ok(removed_placement)
     }
 
-    pub(crate) fn piece_id_grid(&self) -> Vec<Vec<i32>> {
-        let mut result: Vec<Vec<i32>> =
-            vec![vec![-1; usize::from(self.width)]; usize::from(self.height)];
+    pub fn piece_id_grid(&self) -> Vec<Vec<i32>> {
+        let mut result: Vec<Vec<i32>> = vec![vec![-1; self.width]; self.height];
 
         for placement in self.placements.iter() {
-            for piece_row in 0..placement.piece.height {
-                for piece_column in 0..placement.piece.width {
-                    if placement.piece.is_solid(piece_row, piece_column) {
-                        result[usize::from(placement.row + piece_row)]
-                            [usize::from(placement.column + piece_column)] = placement.piece.id;
-                    }
-                }
+            for (row, column) in self.covered_cells(placement) {
+                result[row][column] = placement.piece.id;
             }
         }
 
         result
     }
 
-    fn number_of_top_level_possibilities(&self, transforms: &[Piece]) -> u32 {
-        transforms
-            .iter()
-            .map(|p| ((1 + (self.width - p.width) / 2) * (1 + (self.height - p.height) / 2)) as u32)
-            .sum()
-    }
+    // Collects the connected empty region containing `start`, using an explicit stack so large
+    // regions can't overflow the call stack.
+    fn region_from(&self, visited: &mut [bool], start: usize) -> Vec<usize> {
+        let (width, height) = (self.width, self.height);
+        let mut region = vec![];
+        let mut pending = vec![start];
+        visited[start] = true;
 
-    fn count_from(&self, visited: &mut [bool], row: u8, column: u8) -> u32 {
-        let index = (row * self.width + column) as usize;
-        if visited[index] || self.filled[index] {
-            return 0;
-        }
+        while let Some(index) = pending.pop() {
+            region.push(index);
 
-        let mut result = 1;
-        visited[index] = true;
+            let row = index / width;
+            let column = index % width;
+            let wraps_rows = self.topology.wraps_rows();
+            let wraps_columns = self.topology.wraps_columns();
+            let neighbours = [
+                (row > 0).then(|| index - width),
+                (row < height - 1).then(|| index + width),
+                (column > 0).then(|| index - 1),
+                (column < width - 1).then(|| index + 1),
+                (wraps_rows && row == 0).then(|| index + (height - 1) * width),
+                (wraps_rows && row == height - 1).then_some(column),
+                (wraps_columns && column == 0).then(|| index + width - 1),
+                (wraps_columns && column == width - 1).then(|| index + 1 - width),
+            ];
 
-        if row > 0 {
-            result += self.count_from(visited, row - 1, column);
-        }
-        if row < self.height - 1 {
-            result += self.count_from(visited, row + 1, column);
-        }
-        if column > 0 {
-            result += self.count_from(visited, row, column - 1);
-        }
-        if column < self.width - 1 {
-            result += self.count_from(visited, row, column + 1);
+            for neighbour in neighbours.into_iter().flatten() {
+                if !visited[neighbour] && !self.filled[neighbour] {
+                    visited[neighbour] = true;
+                    pending.push(neighbour);
+                }
+            }
         }
 
-        result
+        region
     }
 
-    pub(crate) fn empty_spaces_multiple_of_five(&self) -> bool {
-        let mut visited = vec![false; self.width as usize * self.height as usize];
+    fn empty_regions(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.filled.len()];
+        let mut regions = vec![];
 
         for (i, filled) in self.filled.iter().enumerate() {
-            if !filled
-                && !visited[i]
-                && self.count_from(
-                    visited.as_mut_slice(),
-                    i as u8 / self.width,
-                    i as u8 % self.width,
-                ) % 5
-                    != 0
-            {
-                return false;
+            if !filled && !visited[i] {
+                regions.push(self.region_from(&mut visited, i));
             }
         }
 
-        true
+        regions
+    }
+
+    pub fn empty_spaces_multiple_of_five(&self) -> bool {
+        self.empty_regions()
+            .iter()
+            .all(|region| region.len().is_multiple_of(5))
+    }
+
+    // A region is dead when none of the remaining pieces fit in its bounding box, or when it is
+    // exactly five cells and doesn't match the shape of any remaining transform. Regions that
+    // reach across a joined edge have no meaningful bounding box, so are left alone.
+    fn is_dead_region(&self, region: &[usize], remaining: &[&Vec<Piece>]) -> bool {
+        let width = self.width;
+        let top = region.iter().map(|i| i / width).min().unwrap();
+        let bottom = region.iter().map(|i| i / width).max().unwrap();
+        let left = region.iter().map(|i| i % width).min().unwrap();
+        let right = region.iter().map(|i| i % width).max().unwrap();
+        let region_height = bottom - top + 1;
+        let region_width = right - left + 1;
+
+        if (self.topology.wraps_rows() && region_height == self.height)
+            || (self.topology.wraps_columns() && region_width == width)
+        {
+            return false;
+        }
+
+        let fits_bounds = |transform: &Piece| {
+            usize::from(transform.height) <= region_height
+                && usize::from(transform.width) <= region_width
+        };
+
+        if region.len() != 5 {
+            return !remaining.iter().flat_map(|t| t.iter()).any(fits_bounds);
+        }
+
+        let mut shape = vec![false; region_width * region_height];
+        for index in region {
+            shape[(index / width - top) * region_width + index % width - left] = true;
+        }
+
+        !remaining
+            .iter()
+            .flat_map(|t| t.iter())
+            .any(|transform| fits_bounds(transform) && transform.shape == shape)
+    }
+
+    pub fn has_dead_region(&self, remaining: &[&Vec<Piece>]) -> bool {
+        self.empty_regions()
+            .iter()
+            .any(|region| self.is_dead_region(region, remaining))
     }
 
-    pub(crate) fn print_state(&self) {
+    /// The board drawn with a differently coloured background for each piece, using terminal
+    /// escape codes, one line per row.
+    pub fn render(&self) -> String {
+        let mut rendered = String::new();
+
         for row in self.piece_id_grid() {
             for piece_id in row {
                 if piece_id == -1 {
-                    print!("\u{001b}[0m ");
+                    rendered += "\u{001b}[0m ";
                 } else {
-                    print!("\u{001b}[48;5;{}m ", piece_id);
+                    rendered += &format!("\u{001b}[48;5;{}m ", piece_id);
+                }
+            }
+            rendered += "\u{001b}[0m\n";
+        }
+
+        rendered
+    }
+
+    fn is_full(&self) -> bool {
+        self.filled.iter().all(|f| *f)
+    }
+
+    // Complete once every required piece is placed, which when some pieces are optional also
+    // means the board is full.
+    fn is_complete(&self, supply: &[Supply]) -> bool {
+        !supply.iter().any(Supply::required)
+            && (self.is_full() || !supply.iter().any(Supply::optional))
+    }
+
+    fn is_checkerboard(&self) -> bool {
+        !self.colours.is_empty()
+            && (0..self.colours.len()).all(|i| {
+                let (row, column) = (i / self.width, i % self.width);
+                (self.colours[i] == self.colours[0]) == ((row + column) % 2 == 0)
+            })
+    }
+
+    /// Whether the dark cells left empty on a coloured board can't be covered by the pieces
+    /// still to place. Only applies when every remaining piece has to be placed and together
+    /// they exactly fill the empty cells.
+    pub fn parity_impossible(&self, transforms: &[Vec<Piece>], supply: &[Supply]) -> bool {
+        if self.colours.is_empty() || supply.iter().any(Supply::optional) {
+            return false;
+        }
+
+        let empty: Vec<usize> = (0..self.filled.len())
+            .filter(|i| !self.filled[*i])
+            .collect();
+        let remaining: Vec<&Piece> = transforms
+            .iter()
+            .zip(supply.iter())
+            .flat_map(|(t, s)| match s {
+                Supply::Exactly(count) => vec![&t[0]; *count as usize],
+                _ => vec![],
+            })
+            .collect();
+        if remaining.len() * 5 != empty.len() {
+            return false;
+        }
+
+        // Which numbers of dark cells the pieces so far can cover between them, which never
+        // exceeds their five cells each.
+        let checkerboard = self.is_checkerboard();
+        let mut reachable = vec![false; empty.len() + 1];
+        reachable[0] = true;
+        for piece in remaining {
+            let counts = piece.dark_cell_counts(checkerboard);
+            let mut next = vec![false; reachable.len()];
+
+            for (dark, _) in reachable.iter().enumerate().filter(|(_, r)| **r) {
+                for count in counts.iter() {
+                    next[dark + count] = true;
                 }
             }
-            println!("\u{001b}[0m");
+            reachable = next;
+        }
+
+        !reachable[empty.iter().filter(|i| self.colours[**i]).count()]
+    }
+
+    fn should_prune(&self, transforms: &[Vec<Piece>], supply: &[Supply]) -> bool {
+        let remaining: Vec<&Vec<Piece>> = transforms
+            .iter()
+            .zip(supply.iter())
+            .filter(|(_, s)| s.available())
+            .map(|(t, _)| t)
+            .collect();
+
+        let prune = !self.empty_spaces_multiple_of_five()
+            || self.has_dead_region(&remaining)
+            || self.parity_impossible(transforms, supply);
+        #[cfg(feature = "trace")]
+        if prune {
+            println!("Pruning impossible path:");
+            print!("{}", self.render());
         }
+
+        prune
     }
 
     fn found_solution(&self) -> String {
         let name_grid = self.name_grid();
-        println!("Found solution: {}", name_grid);
-        self.print_state();
+        report(&self.reporter, &SearchEvent::Solution(&name_grid));
 
         name_grid
     }
 
-    fn place_remaining_pieces(&mut self, remaining: &'a [Vec<Piece>]) -> Vec<String> {
-        if !self.empty_spaces_multiple_of_five() {
-            #[cfg(feature = "trace")]
-            {
-                println!("Pruning impossible path:");
-                self.print_state();
-            }
+    // Pieces are placed group by group. Copies of the same piece are placed in increasing
+    // (transform, row, column) order so each arrangement is only found once, and groups that
+    // aren't required can be skipped to move on to the next one.
+    fn place_remaining_pieces(
+        &mut self,
+        transforms: &'a [Vec<Piece>],
+        group: usize,
+        after: Option<(usize, usize, usize)>,
+        supply: &mut [Supply],
+        statistics: &mut SearchStatistics,
+    ) -> Vec<String> {
+        if self.is_complete(supply) {
+            return vec![self.found_solution()];
+        }
+        if self.should_prune(transforms, supply) {
+            statistics.record_prune();
+            return vec![];
+        }
+        if group == transforms.len() {
             return vec![];
         }
 
         let mut solutions = vec![];
-        for transform in remaining[0].iter() {
-            for row in 0..(1 + self.height - transform.height) {
-                for column in 0..(1 + self.width - transform.width) {
+        if !supply[group].required() {
+            let mut child_solutions =
+                self.place_remaining_pieces(transforms, group + 1, None, supply, statistics);
+            solutions.append(&mut child_solutions);
+        }
+        if !supply[group].available() {
+            return solutions;
+        }
+
+        for (transform_index, transform) in transforms[group].iter().enumerate() {
+            if stopped(self.stop) {
+                break;
+            }
+            if !self.can_contain(transform) {
+                continue;
+            }
+
+            let (rows, columns) = self.positions(transform);
+            for row in 0..rows {
+                for column in 0..columns {
+                    if after.is_some_and(|a| (transform_index, row, column) <= a) {
+                        continue;
+                    }
+
                     let placement = Placement {
                         column,
                         row,
                         piece: transform,
                     };
 
-                    if self.try_add(placement) {
-                        if remaining.len() == 1 {
-                            solutions.push(self.found_solution());
+                    let added = self.try_add(placement);
+                    statistics.record_placement(added);
+                    if added {
+                        statistics.record_node(self.placements.len());
+                        supply[group].take();
+                        let mut child_solutions = if supply[group].available() {
+                            let after = Some((transform_index, row, column));
+                            self.place_remaining_pieces(
+                                transforms, group, after, supply, statistics,
+                            )
                         } else {
-                            let mut child_solutions = self.place_remaining_pieces(&remaining[1..]);
-                            solutions.append(&mut child_solutions);
-                        }
+                            self.place_remaining_pieces(
+                                transforms,
+                                group + 1,
+                                None,
+                                supply,
+                                statistics,
+                            )
+                        };
+                        solutions.append(&mut child_solutions);
+                        supply[group].give_back();
                         self.remove_last();
                     }
                 }
             }
         }
 
-        solutions
+        solutions
+    }
+
+    /// Every placement of a piece with some supply left that fits on the board as it is, with
+    /// the index of the piece's group.
+    pub(crate) fn legal_placements<'b>(
+        &self,
+        transforms: &'b [Vec<Piece>],
+        supply: &[Supply],
+    ) -> Vec<(usize, Placement<'b>)> {
+        let mut placements = vec![];
+
+        for (group, group_transforms) in transforms.iter().enumerate() {
+            if !supply[group].available() {
+                continue;
+            }
+
+            for transform in group_transforms.iter().filter(|t| self.can_contain(t)) {
+                let (rows, columns) = self.positions(transform);
+                for row in 0..rows {
+                    for column in 0..columns {
+                        let placement = Placement {
+                            row,
+                            column,
+                            piece: transform,
+                        };
+                        if self.in_bounds(&placement) && self.fits(&placement) {
+                            placements.push((group, placement));
+                        }
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+
+    // The board with its fixed pieces placed, and the supply left after placing them.
+    pub(crate) fn with_fixed_pieces(
+        &self,
+        transforms: &'a [Vec<Piece>],
+        supply: &[Supply],
+    ) -> Result<(Board<'a>, Vec<Supply>), Error> {
+        let mut remaining = supply.to_vec();
+        let mut board = self.clone();
+
+        for fixed in self.constraints.fixed.iter() {
+            let missing = || Error::MissingTransform {
+                piece_id: fixed.piece_id,
+                transform: fixed.transform,
+            };
+            let group = transforms
+                .iter()
+                .position(|t| t[0].id == fixed.piece_id)
+                .ok_or_else(missing)?;
+            let piece = transforms[group].get(fixed.transform).ok_or_else(missing)?;
+
+            board.try_place(Placement {
+                row: fixed.row,
+                column: fixed.column,
+                piece,
+            })?;
+            remaining[group].take();
+        }
+
+        Ok((board, remaining))
+    }
+
+    // Every placement of an available piece that would cover the given empty cell.
+    fn covering_moves(
+        &self,
+        transforms: &[Vec<Piece>],
+        supply: &[Supply],
+        row: usize,
+        column: usize,
+    ) -> Vec<Move> {
+        let mut moves = vec![];
+
+        for (piece_index, piece_transforms) in transforms.iter().enumerate() {
+            if !supply[piece_index].available() {
+                continue;
+            }
+
+            for (transform_index, transform) in piece_transforms.iter().enumerate() {
+                if !self.can_contain(transform) {
+                    continue;
+                }
+
+                for piece_row in 0..transform.height {
+                    for piece_column in 0..transform.width {
+                        let start = (
+                            origin(row, piece_row, self.height, self.topology.wraps_rows()),
+                            origin(
+                                column,
+                                piece_column,
+                                self.width,
+                                self.topology.wraps_columns(),
+                            ),
+                        );
+                        let (Some(start_row), Some(start_column)) = start else {
+                            continue;
+                        };
+                        let placement = Placement {
+                            row: start_row,
+                            column: start_column,
+                            piece: transform,
+                        };
+
+                        if transform.is_solid(piece_row, piece_column)
+                            && self.in_bounds(&placement)
+                            && self.fits(&placement)
+                        {
+                            moves.push(Move {
+                                piece: piece_index,
+                                transform: transform_index,
+                                row: placement.row,
+                                column: placement.column,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn most_constrained_moves(&self, transforms: &[Vec<Piece>], supply: &[Supply]) -> Vec<Move> {
+        let mut best: Option<Vec<Move>> = None;
+
+        for row in 0..self.height {
+            for column in 0..self.width {
+                if !self.empty(row, column) {
+                    continue;
+                }
+
+                let moves = self.covering_moves(transforms, supply, row, column);
+                if best.as_ref().is_none_or(|b| moves.len() < b.len()) {
+                    if moves.len() <= 1 {
+                        return moves;
+                    }
+                    best = Some(moves);
+                }
+            }
+        }
+
+        best.unwrap_or_default()
+    }
+
+    fn place_most_constrained(
+        &mut self,
+        transforms: &'a [Vec<Piece>],
+        supply: &mut [Supply],
+        statistics: &mut SearchStatistics,
+    ) -> Vec<String> {
+        if self.is_complete(supply) {
+            return vec![self.found_solution()];
+        }
+        if self.should_prune(transforms, supply) {
+            statistics.record_prune();
+            return vec![];
+        }
+
+        let mut solutions = vec![];
+        for next_move in self.most_constrained_moves(transforms, supply) {
+            if stopped(self.stop) {
+                break;
+            }
+            let placement = Placement {
+                row: next_move.row,
+                column: next_move.column,
+                piece: &transforms[next_move.piece][next_move.transform],
+            };
+
+            let added = self.try_add(placement);
+            statistics.record_placement(added);
+            if added {
+                statistics.record_node(self.placements.len());
+                supply[next_move.piece].take();
+                let mut child_solutions =
+                    self.place_most_constrained(transforms, supply, statistics);
+                solutions.append(&mut child_solutions);
+                supply[next_move.piece].give_back();
+                self.remove_last();
+            }
+        }
+
+        solutions
+    }
+
+    // Cells in order along the longer side of the board, so on narrow boards the filled cells
+    // always form a short frontier and far more states repeat.
+    fn fill_order(&self) -> Vec<usize> {
+        let (width, height) = (self.width, self.height);
+
+        if width > height {
+            (0..width)
+                .flat_map(|column| (0..height).map(move |row| row * width + column))
+                .collect()
+        } else {
+            (0..width * height).collect()
+        }
+    }
+
+    fn filled_key(&self) -> Vec<u64> {
+        let mut key = vec![0; self.filled.len().div_ceil(64)];
+        for (i, _) in self.filled.iter().enumerate().filter(|(_, f)| **f) {
+            key[i / 64] |= 1 << (i % 64);
+        }
+
+        key
+    }
+
+    // Fills the first empty cell in `order` with every piece that covers it, adding up the
+    // counts below each and remembering them for the next time the same state comes up.
+    fn count_from_first_empty(
+        &mut self,
+        transforms: &'a [Vec<Piece>],
+        supply: &mut [Supply],
+        order: &[usize],
+        table: &mut TranspositionTable,
+        statistics: &mut SearchStatistics,
+    ) -> u64 {
+        let Some(first_empty) = order.iter().find(|i| !self.filled[**i]) else {
+            return u64::from(!supply.iter().any(Supply::required));
+        };
+        let key = (self.filled_key(), supply.to_vec());
+        if let Some(count) = table.entries.get(&key) {
+            table.hits += 1;
+            return *count;
+        }
+
+        let mut count = 0;
+        if self.should_prune(transforms, supply) {
+            statistics.record_prune();
+        } else {
+            let (row, column) = (first_empty / self.width, first_empty % self.width);
+            for next_move in self.covering_moves(transforms, supply, row, column) {
+                if stopped(self.stop) {
+                    return count;
+                }
+
+                let placement = Placement {
+                    row: next_move.row,
+                    column: next_move.column,
+                    piece: &transforms[next_move.piece][next_move.transform],
+                };
+                let added = self.try_add(placement);
+                statistics.record_placement(added);
+                if added {
+                    statistics.record_node(self.placements.len());
+                    supply[next_move.piece].take();
+                    count +=
+                        self.count_from_first_empty(transforms, supply, order, table, statistics);
+                    supply[next_move.piece].give_back();
+                    self.remove_last();
+                }
+            }
+        }
+
+        if table.entries.len() < table.capacity {
+            table.entries.insert(key, count);
+        }
+        count
+    }
+
+    // An empty board of the same shape, holes, colours, constraints and topology, which doesn't
+    // borrow the pieces placed on this one.
+    fn empty_copy<'b>(&self) -> Board<'b> {
+        let mut filled = self.filled.clone();
+        for placement in self.placements.iter() {
+            for (row, column) in self.covered_cells(placement) {
+                filled[row * self.width + column] = false;
+            }
+        }
+
+        Board {
+            width: self.width,
+            height: self.height,
+            placements: vec![],
+            filled,
+            constraints: self.constraints.clone(),
+            colours: self.colours.clone(),
+            topology: self.topology,
+            stop: self.stop,
+            threads: self.threads,
+            reporter: self.reporter.clone(),
+            cut_symmetry: self.cut_symmetry,
+        }
+    }
+
+    // The moves placing the fixed pieces, or None when one refers to a missing piece or
+    // transform.
+    fn fixed_moves(&self, transforms: &[Vec<Piece>]) -> Option<Vec<Move>> {
+        self.constraints
+            .fixed
+            .iter()
+            .map(|fixed| {
+                let piece = transforms.iter().position(|t| t[0].id == fixed.piece_id)?;
+
+                (fixed.transform < transforms[piece].len()).then_some(Move {
+                    piece,
+                    transform: fixed.transform,
+                    row: fixed.row,
+                    column: fixed.column,
+                })
+            })
+            .collect()
+    }
+
+    // Places each move and takes its piece from the supply, stopping at the first one that
+    // doesn't fit.
+    fn apply_moves(
+        &mut self,
+        transforms: &'a [Vec<Piece>],
+        moves: &[Move],
+        supply: &mut [Supply],
+    ) -> bool {
+        for next_move in moves {
+            let placement = Placement {
+                row: next_move.row,
+                column: next_move.column,
+                piece: &transforms[next_move.piece][next_move.transform],
+            };
+
+            if !supply[next_move.piece].available()
+                || !self.in_bounds(&placement)
+                || !self.try_add(placement)
+            {
+                return false;
+            }
+            supply[next_move.piece].take();
+        }
+
+        true
+    }
+
+    // Whether the board's holes and colours are unchanged by moving each cell (row, column) to
+    // `moved(row, column)`, so the move turns solutions into solutions.
+    fn invariant_under(&self, moved: impl Fn(usize, usize) -> (usize, usize)) -> bool {
+        (0..self.width * self.height).all(|index| {
+            let (row, column) = moved(index / self.width, index % self.width);
+            let image = row * self.width + column;
+            self.filled[index] == self.filled[image]
+                && (self.colours.is_empty() || self.colours[index] == self.colours[image])
+        })
+    }
+
+    // Placements of the first piece restricted to the top left quadrant, as the rest can be
+    // found by reflecting solutions. Reflections aren't available when any piece is one-sided,
+    // leaving only the 180 degree rotation, so then the first piece covers the whole top half.
+    // Along a joined axis every solution can be shifted to start the first piece at 0.
+    // Constraints break the symmetry altogether, and holes and colours break whichever moves
+    // don't map them onto themselves. Without `cut_symmetry` every placement is kept.
+    fn top_level_moves(&self, transforms: &[Vec<Piece>], cut_symmetry: bool) -> Vec<Move> {
+        let symmetric = cut_symmetry && self.constraints.is_empty();
+        let reflectable = symmetric && all_reflectable(transforms);
+        let (width, height) = (self.width, self.height);
+        let shifts_columns = symmetric
+            && self.topology.wraps_columns()
+            && self.invariant_under(|row, column| (row, (column + 1) % width));
+        let shifts_rows = symmetric
+            && self.topology.wraps_rows()
+            && self.invariant_under(|row, column| ((row + 1) % height, column));
+        let flips_columns =
+            reflectable && self.invariant_under(|row, column| (row, width - 1 - column));
+        let flips_rows =
+            reflectable && self.invariant_under(|row, column| (height - 1 - row, column));
+        let rotates =
+            symmetric && self.invariant_under(|row, column| (height - 1 - row, width - 1 - column));
+        // Halving both axes needs both reflections, otherwise one reflection or the rotation
+        // halves just one of them.
+        let (halve_columns, halve_rows) = match (shifts_columns, shifts_rows) {
+            (true, true) => (false, false),
+            (true, false) => (false, flips_rows || rotates),
+            (false, true) => (flips_columns || rotates, false),
+            (false, false) if flips_columns && flips_rows => (true, true),
+            (false, false) => (false, flips_rows || rotates),
+        };
+        let mut moves = vec![];
+
+        for (transform_index, transform) in transforms[0].iter().enumerate() {
+            if !self.can_contain(transform) {
+                continue;
+            }
+
+            let (all_rows, all_columns) = self.positions(transform);
+            let columns = if shifts_columns {
+                1
+            } else if halve_columns {
+                1 + (self.width - usize::from(transform.width)) / 2
+            } else {
+                all_columns
+            };
+            let rows = if shifts_rows {
+                1
+            } else if halve_rows {
+                1 + (self.height - usize::from(transform.height)) / 2
+            } else {
+                all_rows
+            };
+
+            for column in 0..columns {
+                for row in 0..rows {
+                    moves.push(Move {
+                        piece: 0,
+                        transform: transform_index,
+                        row,
+                        column,
+                    });
+                }
+            }
+        }
+
+        moves
+    }
+
+    pub fn find_solutions(
+        &mut self,
+        transforms: &'a Arc<Vec<Vec<Piece>>>,
+        branching: Branching,
+    ) -> SearchResult {
+        self.find_solutions_with_supply(
+            transforms,
+            &vec![Supply::Exactly(1); transforms.len()],
+            branching,
+        )
+    }
+
+    /// Searches for solutions using each piece as many times as its supply allows. When any
+    /// piece is optional the board has to be filled completely, so a pool of pieces larger than
+    /// the board needs finds every subset that fits.
+    pub fn find_solutions_with_supply(
+        &mut self,
+        transforms: &'a Arc<Vec<Vec<Piece>>>,
+        supply: &[Supply],
+        branching: Branching,
+    ) -> SearchResult {
+        self.search(transforms, supply, branching, self.cut_symmetry)
+    }
+
+    // The search behind `find_solutions_with_supply`, which can be told not to leave out
+    // solutions that are reflections or rotations of ones it finds.
+    fn search(
+        &mut self,
+        transforms: &'a Arc<Vec<Vec<Piece>>>,
+        supply: &[Supply],
+        branching: Branching,
+        cut_symmetry: bool,
+    ) -> SearchResult {
+        let mut statistics = SearchStatistics::default();
+        statistics.record_node(0);
+
+        let mut start_supply = supply.to_vec();
+        let placed_before = self.placements.len();
+        let fixed_moves = match self.fixed_moves(transforms) {
+            Some(moves) if self.apply_moves(transforms, &moves, &mut start_supply) => moves,
+            _ => {
+                while self.placements.len() > placed_before {
+                    self.remove_last();
+                }
+                return SearchResult {
+                    solutions: vec![],
+                    statistics,
+                    complete: true,
+                };
+            }
+        };
+
+        // The top level is split into one thread per first move, except when the piece order
+        // search may skip or repeat the first piece, or the fixed pieces already finish the
+        // puzzle, when it all runs from the starting board.
+        let split_top_level = branching == Branching::MostConstrainedCell
+            || start_supply.first() == Some(&Supply::Exactly(1));
+        let top_level_moves: Vec<Option<Move>> = match branching {
+            _ if !split_top_level || self.is_complete(&start_supply) => vec![None],
+            Branching::PieceOrder => self
+                .top_level_moves(transforms, cut_symmetry)
+                .into_iter()
+                .map(Some)
+                .collect(),
+            Branching::MostConstrainedCell => self
+                .most_constrained_moves(transforms, &start_supply)
+                .into_iter()
+                .map(Some)
+                .collect(),
+        };
+        while self.placements.len() > placed_before {
+            self.remove_last();
+        }
+        let total = top_level_moves.len();
+        let mut done = 0;
+        let reporter = self.reporter.clone();
+        let mut output_progress = move || {
+            report(&reporter, &SearchEvent::Progress { done, total });
+            done += 1;
+        };
+        output_progress();
+        let mut solutions = vec![];
+        let subtrees = top_level_moves.into_iter().map(|top_level_move| {
+            let child_template = self.empty_copy();
+            let child_fixed_moves = fixed_moves.clone();
+            let child_pieces = Arc::clone(transforms);
+            let mut child_supply = supply.to_vec();
+
+            move || {
+                let start = Instant::now();
+                let mut statistics = SearchStatistics::default();
+                let mut child_board = child_template.empty_copy();
+                child_board.apply_moves(&child_pieces, &child_fixed_moves, &mut child_supply);
+
+                let solutions = match top_level_move {
+                    None => match branching {
+                        Branching::PieceOrder => child_board.place_remaining_pieces(
+                            &child_pieces,
+                            0,
+                            None,
+                            &mut child_supply,
+                            &mut statistics,
+                        ),
+                        Branching::MostConstrainedCell => child_board.place_most_constrained(
+                            &child_pieces,
+                            &mut child_supply,
+                            &mut statistics,
+                        ),
+                    },
+                    Some(top_level_move) => {
+                        let added = child_board.apply_moves(
+                            &child_pieces,
+                            &[top_level_move],
+                            &mut child_supply,
+                        );
+                        statistics.record_placement(added);
+
+                        if !added {
+                            vec![]
+                        } else {
+                            statistics.record_node(child_board.placements.len());
+                            match branching {
+                                Branching::PieceOrder => child_board.place_remaining_pieces(
+                                    &child_pieces,
+                                    1,
+                                    None,
+                                    &mut child_supply,
+                                    &mut statistics,
+                                ),
+                                Branching::MostConstrainedCell => child_board
+                                    .place_most_constrained(
+                                        &child_pieces,
+                                        &mut child_supply,
+                                        &mut statistics,
+                                    ),
+                            }
+                        }
+                    }
+                };
+
+                statistics.solutions_per_subtree.push(solutions.len());
+                statistics.thread_times.push(start.elapsed());
+                (solutions, statistics)
+            }
+        });
+        search_subtrees(
+            subtrees,
+            self.threads,
+            |child_solutions, child_statistics| {
+                output_progress();
+
+                solutions.extend_from_slice(&child_solutions);
+                statistics.merge(&child_statistics);
+            },
+        );
+        SearchResult {
+            solutions,
+            statistics,
+            complete: !stopped(self.stop),
+        }
+    }
+
+    /// Solves the board, honouring its constraints, and reports whether exactly one name grid
+    /// solves it. Copies of the same piece swapping places count as the same solution, but
+    /// reflections and rotations of a solution count as different ones whichever branching is
+    /// used.
+    pub fn check_uniqueness(
+        &mut self,
+        transforms: &'a Arc<Vec<Vec<Piece>>>,
+        supply: &[Supply],
+        branching: Branching,
+    ) -> Uniqueness {
+        let result = self.search(transforms, supply, branching, false);
+        let mut solutions = result.solutions;
+        solutions.sort();
+        solutions.dedup();
+
+        match solutions.len() {
+            0 | 1 if !result.complete => Uniqueness::Interrupted,
+            0 => Uniqueness::NoSolution,
+            1 => Uniqueness::Unique(solutions.remove(0)),
+            _ => Uniqueness::Ambiguous(solutions),
+        }
+    }
+
+    /// Counts the ways to fill every cell of the board with the given supply, without listing
+    /// them. Many orders of placing pieces reach the same board with the same pieces left, so
+    /// counts are kept in a table of up to about `memory_budget` bytes and reused. Solutions
+    /// that are rotations or reflections of each other are counted separately.
+    pub fn count_solutions(
+        &mut self,
+        transforms: &'a [Vec<Piece>],
+        supply: &[Supply],
+        memory_budget: usize,
+    ) -> SolutionCount {
+        let mut statistics = SearchStatistics::default();
+        statistics.record_node(0);
+
+        let entry_size = 2 * size_of::<Vec<u64>>()
+            + size_of::<u64>() * (1 + self.filled.len().div_ceil(64))
+            + size_of_val(supply);
+        let mut table = TranspositionTable {
+            entries: HashMap::new(),
+            capacity: memory_budget / entry_size,
+            hits: 0,
+        };
+
+        let mut remaining = supply.to_vec();
+        let placed_before = self.placements.len();
+        let fixed_fit = self
+            .fixed_moves(transforms)
+            .is_some_and(|moves| self.apply_moves(transforms, &moves, &mut remaining));
+        let count = if fixed_fit {
+            let order = self.fill_order();
+            self.count_from_first_empty(
+                transforms,
+                &mut remaining,
+                &order,
+                &mut table,
+                &mut statistics,
+            )
+        } else {
+            0
+        };
+        while self.placements.len() > placed_before {
+            self.remove_last();
+        }
+
+        SolutionCount {
+            count,
+            statistics,
+            table_hits: table.hits,
+            table_entries: table.entries.len(),
+            complete: !stopped(self.stop),
+        }
+    }
+
+    pub fn name_grid(&self) -> String {
+        let mut buffer = vec![vec!['.'; self.width]; self.height];
+
+        for placement in self.placements.iter() {
+            let piece_name = placement.piece.name_char();
+
+            for (row, column) in self.covered_cells(placement) {
+                buffer[row][column] = piece_name
+            }
+        }
+
+        let rows: Vec<String> = buffer.into_iter().map(|c| c.iter().collect()).collect();
+
+        rows.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{
+        create_board, create_board_with_holes, create_checkerboard, create_cylinder, create_torus,
+        Board, Branching, Collision, Placement, Supply, Topology, Uniqueness,
+    };
+    use crate::canonical::canonicalise_wrapped_solution_string;
+    use crate::constraints::{Constraints, FixedPiece};
+    use crate::error::Error;
+    use crate::parse::piece_names;
+    use crate::pieces::{
+        checkered_piece_from_name, mirrored_piece_from_name, one_sided_piece_from_name,
+        piece_from_name, standard_pieces, PentominoName, Piece,
+    };
+    use crate::report::{Reporter, SearchEvent};
+    use crate::testing::u_u_x_i_transforms;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn can_add_to_empty_board() {
+        let piece = piece_from_name(1, PentominoName::U);
+        let placement = Placement {
+            row: 0,
+            column: 0,
+            piece: &piece,
+        };
+        let mut board = create_board(5, 2);
+
+        assert!(board.try_add(placement));
+    }
+
+    #[test]
+    fn cannot_add_to_full_board() {
+        let first_piece = piece_from_name(1, PentominoName::I);
+        let second_piece = piece_from_name(2, PentominoName::N);
+        let mut board = create_board(5, 2);
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &first_piece,
+        });
+
+        assert!(!board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &second_piece
+        }));
+    }
+
+    #[test]
+    fn can_add_after_removing() {
+        let first_piece = piece_from_name(1, PentominoName::I);
+        let second_piece = piece_from_name(2, PentominoName::N);
+
+        let mut board = create_board(5, 3);
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &first_piece,
+        });
+        board.remove_last();
+
+        assert!(board.try_add(Placement {
+            row: 0,
+            column: 1,
+            piece: &second_piece
+        }));
+    }
+
+    #[test]
+    fn explains_why_a_placement_does_not_fit() {
+        let i_piece = piece_from_name(1, PentominoName::I);
+        let n_piece = piece_from_name(2, PentominoName::N);
+        let dark_x = checkered_piece_from_name(3, PentominoName::X, true);
+        let mut board = create_checkerboard(5, 4);
+        let placement = |row, column, piece| Placement { row, column, piece };
+
+        let problems = board.check_placement(&placement(0, 3, &i_piece));
+        assert_eq!(vec![(0, 5), (0, 6), (0, 7)], problems.off_board);
+        assert_eq!(
+            "square at row 0 column 5 is off the board; \
+             square at row 0 column 6 is off the board; \
+             square at row 0 column 7 is off the board",
+            problems.to_string()
+        );
+
+        assert_eq!(Ok(()), board.try_place(placement(0, 0, &i_piece)));
+        assert_eq!(
+            vec![
+                Collision {
+                    row: 0,
+                    column: 0,
+                    placed: Some(0),
+                    placed_name: Some('I')
+                },
+                Collision {
+                    row: 0,
+                    column: 1,
+                    placed: Some(0),
+                    placed_name: Some('I')
+                }
+            ],
+            board.check_placement(&placement(0, 0, &n_piece)).collisions
+        );
+
+        let problems = board.check_placement(&placement(1, 0, &dark_x));
+        assert!(problems.off_board.is_empty() && problems.collisions.is_empty());
+        assert_eq!(
+            vec![(1, 1), (2, 0), (2, 1), (2, 2), (3, 1)],
+            problems.colour_mismatches
+        );
+        assert!(matches!(
+            board.try_place(placement(1, 0, &dark_x)),
+            Err(Error::IllegalPlacement(_))
+        ));
+        assert_eq!(1, board.placements.len());
+        assert!(board.empty(1, 1));
+        assert!(board.check_placement(&placement(1, 0, &n_piece)).is_empty());
+    }
+
+    #[test]
+    fn removing_from_an_empty_board_is_an_error() {
+        let piece = piece_from_name(1, PentominoName::I);
+        let mut board = create_board(5, 1);
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &piece,
+        });
+
+        assert_eq!(1, board.try_remove_last().unwrap().piece.id);
+        assert!(board.empty(0, 0));
+        assert_eq!(Some(Error::NothingToRemove), board.try_remove_last().err());
+    }
+
+    #[test]
+    fn can_test_for_empty_out_of_bounds() {
+        let piece = piece_from_name(1, PentominoName::I);
+        let mut board = create_board(5, 2);
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &piece,
+        });
+
+        assert!(!board.empty(0, 0));
+        assert!(board.empty(1, 0));
+    }
+
+    #[test]
+    fn can_test_for_empty_inside_bounds() {
+        let piece = piece_from_name(1, PentominoName::N);
+        let mut board = create_board(4, 2);
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &piece,
+        });
+
+        assert!(!board.empty(0, 0));
+        assert!(board.empty(1, 0));
+    }
+
+    #[test]
+    fn generates_expected_shape_id_grid() {
+        let first_piece = piece_from_name(100, PentominoName::I);
+        let second_piece = piece_from_name(200, PentominoName::I);
+        let mut board = create_board(5, 2);
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &first_piece,
+        });
+        board.try_add(Placement {
+            row: 1,
+            column: 0,
+            piece: &second_piece,
+        });
+        let shape_id_grid = board.piece_id_grid();
+
+        assert_eq!(
+            vec![vec![100, 100, 100, 100, 100], vec![200, 200, 200, 200, 200]],
+            shape_id_grid
+        );
+    }
+
+    #[test]
+    fn can_calculate_the_number_of_top_level_possibilities() {
+        let piece = piece_from_name(0, PentominoName::I);
+        let transforms = piece.all_transforms();
+        let board = create_board(12, 5);
+
+        assert_eq!(18, board.top_level_moves(&[transforms], true).len())
+    }
+
+    #[test]
+    fn coloured_pieces_only_fit_matching_cells() {
+        let dark_centre = checkered_piece_from_name(1, PentominoName::X, true);
+        let light_centre = checkered_piece_from_name(2, PentominoName::X, false);
+        let mut board = create_checkerboard(4, 3);
+
+        assert!(!board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &light_centre,
+        }));
+        assert!(board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &dark_centre,
+        }));
+        board.remove_last();
+        assert!(board.try_add(Placement {
+            row: 0,
+            column: 1,
+            piece: &light_centre,
+        }));
+    }
+
+    #[test]
+    fn detects_parity_mismatch() {
+        let transforms = |name| vec![piece_from_name(1, name).all_transforms()];
+        let mut board = create_checkerboard(3, 3);
+        for corner in [0, 2, 6, 8] {
+            board.filled[corner] = true;
+        }
+        let supply = [Supply::Exactly(1)];
+
+        // The cross left open has one dark cell, which a T can't cover on its own
+        assert!(!board.parity_impossible(&transforms(PentominoName::X), &supply));
+        assert!(board.parity_impossible(&transforms(PentominoName::T), &supply));
+        assert!(!create_board(3, 3).parity_impossible(&transforms(PentominoName::T), &supply));
+    }
+
+    #[test]
+    fn finds_solutions_with_coloured_pieces() {
+        let checkered = |top_left_dark| {
+            checkered_piece_from_name(1, PentominoName::I, top_left_dark).all_transforms()
+        };
+        let mut board = create_checkerboard(5, 2);
+
+        assert!(board.parity_impossible(
+            &[checkered(true), checkered(true)],
+            &[Supply::Exactly(1); 2]
+        ));
+
+        let pieces = Arc::new(vec![checkered(true), checkered(false)]);
+        let solutions = board
+            .find_solutions(&pieces, Branching::PieceOrder)
+            .solutions;
+
+        assert_eq!(vec!["IIIII IIIII"], solutions);
+    }
+
+    #[test]
+    fn coloured_boards_find_every_solution_whatever_the_branching() {
+        let checkered = |id, top_left_dark| {
+            checkered_piece_from_name(id, PentominoName::L, top_left_dark).all_transforms()
+        };
+        let pieces = Arc::new(vec![
+            checkered(1, true),
+            checkered(2, true),
+            checkered(3, false),
+            checkered(4, false),
+        ]);
+        let supply = [Supply::Exactly(1); 4];
+
+        let counted = create_checkerboard(5, 4).count_solutions(&pieces, &supply, 1 << 20);
+        assert!(counted.count > 0);
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let result = create_checkerboard(5, 4).find_solutions(&pieces, branching);
+            assert_eq!(counted.count, result.solutions.len() as u64);
+        }
+    }
+
+    #[test]
+    fn pieces_wrap_around_joined_edges() {
+        let i_piece = piece_from_name(1, PentominoName::I);
+        let x_piece = piece_from_name(2, PentominoName::X);
+        let mut cylinder = create_cylinder(5, 3);
+        let mut torus = create_torus(5, 3);
+
+        assert!(cylinder.try_add(Placement {
+            row: 0,
+            column: 3,
+            piece: &i_piece,
+        }));
+        assert!(!cylinder.try_add(Placement {
+            row: 1,
+            column: 4,
+            piece: &x_piece,
+        }));
+        assert!(torus.try_add(Placement {
+            row: 2,
+            column: 4,
+            piece: &x_piece,
+        }));
+
+        assert_eq!("IIIII ..... .....", cylinder.name_grid());
+        assert_eq!("XX..X X.... X....", torus.name_grid());
+        assert!(torus.empty_spaces_multiple_of_five());
+    }
+
+    #[test]
+    fn placements_must_start_on_the_board_even_when_it_wraps() {
+        let x_piece = piece_from_name(2, PentominoName::X);
+        let mut torus = create_torus(5, 3);
+        let placement = Placement {
+            row: 100,
+            column: 7,
+            piece: &x_piece,
+        };
+
+        assert!(!torus.check_placement(&placement).is_empty());
+        assert!(!torus.try_add(placement));
+        assert!(torus.placements.is_empty());
+    }
+
+    #[test]
+    fn cells_off_the_board_are_not_empty() {
+        let board = create_board(5, 2);
+
+        assert!(board.empty(1, 4));
+        assert!(!board.empty(0, 7));
+        assert!(!board.empty(2, 0));
+    }
+
+    #[test]
+    fn can_tile_wrapped_boards_with_unlimited_pieces() {
+        fn count<'a>(mut board: Board<'a>, pieces: &'a Arc<Vec<Vec<Piece>>>) -> usize {
+            board
+                .find_solutions_with_supply(pieces, &[Supply::Unlimited], Branching::PieceOrder)
+                .solutions
+                .len()
+        }
+        let pieces = Arc::new(vec![piece_from_name(1, PentominoName::I).all_transforms()]);
+
+        // Each row of horizontal pieces can be shifted independently around a joined edge
+        assert_eq!(2, count(create_board(5, 5), &pieces));
+        assert_eq!(5usize.pow(5) + 1, count(create_cylinder(5, 5), &pieces));
+        assert_eq!(2 * 5usize.pow(5), count(create_torus(5, 5), &pieces));
+    }
+
+    #[test]
+    fn pieces_fit_around_holes() {
+        let pieces = Arc::new(vec![piece_from_name(1, PentominoName::I).all_transforms()]);
+        let solve = |holes: &[(usize, usize)]| {
+            create_board_with_holes(6, 1, holes)
+                .unwrap()
+                .find_solutions(&pieces, Branching::MostConstrainedCell)
+                .solutions
+        };
+
+        assert_eq!(vec![".IIIII".to_string()], solve(&[(0, 0)]));
+        assert!(solve(&[(0, 2)]).is_empty());
+
+        // Holes stay in place in the threads searching from each first move.
+        let result = create_board_with_holes(6, 2, &[(0, 0), (1, 5)])
+            .unwrap()
+            .find_solutions_with_supply(&pieces, &[Supply::Unlimited], Branching::PieceOrder);
+        assert_eq!(vec![".IIIII IIIII.".to_string()], result.solutions);
+
+        // Holes down the left column break the rotation and the mirroring of columns, leaving
+        // only the mirroring of rows to cut the first piece's moves.
+        let two_pieces = Arc::new(vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::I).all_transforms(),
+        ]);
+        let mut board = create_board_with_holes(6, 2, &[(0, 0), (1, 0)]).unwrap();
+        assert_eq!(
+            vec![".IIIII .IIIII"],
+            board
+                .find_solutions(&two_pieces, Branching::PieceOrder)
+                .solutions
+        );
+        assert_eq!(
+            vec![".IIIII .IIIII"; 2],
+            board
+                .find_solutions(&two_pieces, Branching::MostConstrainedCell)
+                .solutions
+        );
+
+        assert_eq!(
+            Some(Error::HoleOffBoard { row: 2, column: 0 }),
+            create_board_with_holes(6, 2, &[(2, 0)]).err()
+        );
+    }
+
+    #[test]
+    fn handles_boards_larger_than_255_cells() {
+        let piece = piece_from_name(1, PentominoName::I);
+        let mut board = create_board(20, 16);
+
+        assert!(board.try_add(Placement {
+            row: 15,
+            column: 15,
+            piece: &piece,
+        }));
+        assert!(!board.empty(15, 19));
+        assert!(board.empty(14, 19));
+        assert_eq!(
+            (15..20).map(|column| (15, column)).collect::<Vec<_>>(),
+            board.covered_cells(&board.placements[0])
+        );
+        assert!(board.empty_spaces_multiple_of_five());
+
+        let pieces = Arc::new(vec![piece.all_transforms()]);
+        let result = create_board(300, 1).find_solutions_with_supply(
+            &pieces,
+            &[Supply::Unlimited],
+            Branching::MostConstrainedCell,
+        );
+        assert_eq!(1, result.solutions.len());
     }
 
-    pub(crate) fn find_solutions(&mut self, transforms: &'a Arc<Vec<Vec<Piece>>>) -> Vec<String> {
-        let mut output_progress = {
-            let number_of_possibilities =
-                self.number_of_top_level_possibilities(&transforms[0]) as i32;
-            let mut progress = -1i32;
-            move || {
-                progress += 1;
-                println!("{}%", (progress * 100) / number_of_possibilities);
+    #[test]
+    fn thread_limits_keep_the_same_results() {
+        let pieces = u_u_x_i_transforms();
+        let search = |threads| {
+            Board {
+                threads,
+                ..create_board(5, 4)
             }
+            .find_solutions(&pieces, Branching::MostConstrainedCell)
         };
-        let mut solutions = vec![];
-        let mut children = vec![];
-        for (transform_index, transform) in transforms[0].iter().enumerate() {
-            for column in 0..(1 + (self.width - transform.width) / 2) {
-                for row in 0..(1 + (self.height - transform.height) / 2) {
-                    let placement = Placement {
-                        column,
-                        row,
-                        piece: transform,
-                    };
 
-                    if self.try_add(placement) {
-                        if transforms.len() == 1 {
-                            solutions.push(self.found_solution());
-                        } else {
-                            let child_board_width = self.width;
-                            let child_board_height = self.height;
-                            let child_pieces = Arc::clone(transforms);
-
-                            let child_handle = thread::spawn(move || {
-                                let child_placement = Placement {
-                                    row,
-                                    column,
-                                    piece: &child_pieces[0][transform_index],
-                                };
-                                let mut child_board =
-                                    create_board(child_board_width, child_board_height);
-                                child_board.try_add(child_placement);
-
-                                child_board.place_remaining_pieces(&child_pieces[1..])
-                            });
-                            children.push(child_handle);
-                        }
-                        self.remove_last();
-                    }
-                }
-            }
-        }
-
-        for handle in children {
-            let result = handle.join().unwrap();
-            output_progress();
-
-            solutions.extend_from_slice(&result);
+        let unlimited = search(0);
+        for threads in [1, 2] {
+            let limited = search(threads);
+            assert_eq!(unlimited.solutions, limited.solutions);
+            assert_eq!(unlimited.statistics.nodes(), limited.statistics.nodes());
+            assert_eq!(
+                unlimited.statistics.solutions_per_subtree,
+                limited.statistics.solutions_per_subtree
+            );
         }
-        output_progress();
-        solutions
     }
 
-    pub(crate) fn name_grid(&self) -> String {
-        let mut buffer = vec![vec!['.'; self.width as usize]; self.height as usize];
+    #[test]
+    fn reporters_hear_every_solution_and_the_progress() {
+        let pieces = u_u_x_i_transforms();
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut board = create_board(5, 4);
+        let heard = Arc::clone(&events);
+        board.set_reporter(Reporter::new(move |event| {
+            heard.lock().unwrap().push(match event {
+                SearchEvent::Solution(solution) => Ok(solution.to_string()),
+                SearchEvent::Progress { done, total } => Err((*done, *total)),
+            })
+        }));
 
-        for placement in self.placements.iter() {
-            let piece_name = placement.piece.name.name_char();
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            events.lock().unwrap().clear();
+            let mut solutions = board.find_solutions(&pieces, branching).solutions;
+            let events = events.lock().unwrap();
+            let mut reported: Vec<String> = events.iter().filter_map(|e| e.clone().ok()).collect();
+            reported.sort();
+            solutions.sort();
+            assert_eq!(solutions, reported);
 
-            for piece_row in 0..placement.piece.height {
-                for piece_column in 0..placement.piece.width {
-                    if placement.piece.is_solid(piece_row, piece_column) {
-                        buffer[(placement.row + piece_row) as usize]
-                            [(placement.column + piece_column) as usize] = piece_name
-                    }
-                }
-            }
+            let progress: Vec<(usize, usize)> =
+                events.iter().filter_map(|e| e.clone().err()).collect();
+            assert_eq!(Some(&(0, progress[0].1)), progress.first());
+            let (done, total) = progress.last().unwrap();
+            assert_eq!(done, total);
         }
+    }
 
-        let rows: Vec<String> = buffer.into_iter().map(|c| c.iter().collect()).collect();
+    #[test]
+    fn settings_are_read_back() {
+        let mut board = create_cylinder(6, 5);
+        assert_eq!(
+            (6, 5, Topology::Cylinder),
+            (board.width(), board.height(), board.topology())
+        );
+        assert!(board.placements().is_empty());
+        assert!(board.colours().is_empty());
+        assert_eq!(&Constraints::default(), board.constraints());
 
-        rows.join(" ")
+        let constraints = Constraints {
+            fixed: vec![FixedPiece {
+                piece_id: 1,
+                transform: 0,
+                row: 0,
+                column: 0,
+            }],
+            ..Constraints::default()
+        };
+        board.set_constraints(constraints.clone());
+        assert_eq!(&constraints, board.constraints());
+        let dark = create_checkerboard(5, 5)
+            .colours()
+            .iter()
+            .filter(|c| **c)
+            .count();
+        assert_eq!(13, dark);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::board::{create_board, Placement};
-    use crate::pieces::{piece_from_name, PentominoName, Piece};
-    use std::sync::Arc;
 
     #[test]
-    fn can_add_to_empty_board() {
-        let piece = piece_from_name(1, PentominoName::U);
-        let placement = Placement {
-            row: 0,
-            column: 0,
-            piece: &piece,
+    fn stopped_searches_return_what_they_found() {
+        static STOPPED: AtomicBool = AtomicBool::new(true);
+        let pieces = u_u_x_i_transforms();
+        let stopped = || Board {
+            stop: &STOPPED,
+            ..create_board(5, 4)
         };
-        let mut board = create_board(5, 2);
 
-        assert!(board.try_add(placement));
+        let result = stopped().find_solutions(&pieces, Branching::PieceOrder);
+        assert!(!result.complete);
+        assert!(result.solutions.is_empty());
+        assert_eq!(
+            Uniqueness::Interrupted,
+            stopped().check_uniqueness(&pieces, &[Supply::Exactly(1); 4], Branching::PieceOrder)
+        );
+        assert!(
+            create_board(5, 4)
+                .find_solutions(&pieces, Branching::PieceOrder)
+                .complete
+        );
     }
 
     #[test]
-    fn cannot_add_to_full_board() {
-        let first_piece = piece_from_name(1, PentominoName::I);
-        let second_piece = piece_from_name(2, PentominoName::N);
-        let mut board = create_board(5, 2);
-        board.try_add(Placement {
-            row: 0,
-            column: 0,
-            piece: &first_piece,
-        });
+    fn counts_match_the_solutions_found() {
+        let pieces = u_u_x_i_transforms();
+        let count = create_board(5, 4).count_solutions(&pieces, &[Supply::Exactly(1); 4], 1 << 20);
+        assert_eq!(4, count.count);
+        assert!(count.complete);
 
-        assert!(!board.try_add(Placement {
-            row: 0,
-            column: 0,
-            piece: &second_piece
-        }));
+        let all = Arc::new(
+            standard_pieces()
+                .iter()
+                .map(Piece::all_transforms)
+                .collect::<Vec<_>>(),
+        );
+        let subset = vec![Supply::AtMost(1); all.len()];
+        let found = |width, height| {
+            create_board(width, height)
+                .find_solutions_with_supply(&all, &subset, Branching::MostConstrainedCell)
+                .solutions
+                .len() as u64
+        };
+        assert_eq!(
+            found(5, 3),
+            create_board(5, 3)
+                .count_solutions(&all, &subset, 1 << 20)
+                .count
+        );
+        assert_eq!(
+            found(10, 3),
+            create_board(10, 3).count_solutions(&all, &subset, 0).count
+        );
     }
 
     #[test]
-    fn can_add_after_removing() {
-        let first_piece = piece_from_name(1, PentominoName::I);
-        let second_piece = piece_from_name(2, PentominoName::N);
+    fn counting_reuses_repeated_board_states() {
+        let pieces = vec![piece_from_name(1, PentominoName::I).all_transforms()];
 
-        let mut board = create_board(5, 3);
-        board.try_add(Placement {
-            row: 0,
-            column: 0,
-            piece: &first_piece,
-        });
-        board.remove_last();
+        let count = create_torus(5, 5).count_solutions(&pieces, &[Supply::Unlimited], 1 << 20);
+        assert_eq!(2 * 5u64.pow(5), count.count);
+        assert!(count.table_hits > 0);
 
-        assert!(board.try_add(Placement {
-            row: 0,
-            column: 1,
-            piece: &second_piece
-        }));
+        let uncached = create_torus(5, 5).count_solutions(&pieces, &[Supply::Unlimited], 0);
+        assert_eq!(count.count, uncached.count);
+        assert_eq!((0, 0), (uncached.table_hits, uncached.table_entries));
     }
 
     #[test]
-    fn can_test_for_empty_out_of_bounds() {
-        let piece = piece_from_name(1, PentominoName::I);
-        let mut board = create_board(5, 2);
+    fn symmetry_reduction_on_wrapped_boards_keeps_every_solution() {
+        let pieces = u_u_x_i_transforms();
+
+        for topology in [Topology::Cylinder, Topology::Torus] {
+            let unique = |constraints: Constraints| {
+                let mut board = Board {
+                    topology,
+                    constraints,
+                    ..create_board(5, 4)
+                };
+                let mut solutions: Vec<String> = board
+                    .find_solutions(&pieces, Branching::PieceOrder)
+                    .solutions
+                    .iter()
+                    .map(|s| canonicalise_wrapped_solution_string(s, topology, false))
+                    .collect();
+                solutions.sort();
+                solutions.dedup();
+                solutions
+            };
+            // A constraint on a piece that isn't there turns the reduction off
+            let unreduced = unique(Constraints {
+                touch_border: vec![99],
+                ..Default::default()
+            });
+
+            assert!(!unreduced.is_empty());
+            assert_eq!(unreduced, unique(Constraints::default()));
+        }
+    }
+
+    #[test]
+    fn can_check_empty_space_is_multiple_of_five() {
+        let piece = piece_from_name(0, PentominoName::I);
+        let mut board = create_board(12, 5);
         board.try_add(Placement {
             row: 0,
             column: 0,
             piece: &piece,
         });
 
-        assert!(!board.empty(0, 0));
-        assert!(board.empty(1, 0));
+        assert!(board.empty_spaces_multiple_of_five());
     }
 
     #[test]
-    fn can_test_for_empty_inside_bounds() {
-        let piece = piece_from_name(1, PentominoName::N);
-        let mut board = create_board(4, 2);
+    fn can_check_empty_space_in_large_regions() {
+        let piece = piece_from_name(0, PentominoName::I);
+        let mut board = create_board(255, 1);
         board.try_add(Placement {
             row: 0,
             column: 0,
             piece: &piece,
         });
 
-        assert!(!board.empty(0, 0));
-        assert!(board.empty(1, 0));
+        assert!(board.empty_spaces_multiple_of_five());
     }
 
     #[test]
-    fn generates_expected_shape_id_grid() {
-        let first_piece = piece_from_name(100, PentominoName::I);
-        let second_piece = piece_from_name(200, PentominoName::I);
-        let mut board = create_board(5, 2);
-        board.try_add(Placement {
-            row: 0,
-            column: 0,
-            piece: &first_piece,
-        });
+    fn detects_five_cell_region_no_remaining_piece_matches() {
+        let i_piece = piece_from_name(0, PentominoName::I);
+        let remaining_i = piece_from_name(1, PentominoName::I).all_transforms();
+        let remaining_u = piece_from_name(2, PentominoName::U).all_transforms();
+        let mut board = create_board(5, 3);
         board.try_add(Placement {
             row: 1,
             column: 0,
-            piece: &second_piece,
+            piece: &i_piece,
         });
-        let shape_id_grid = board.piece_id_grid();
-
-        assert_eq!(
-            vec![vec![100, 100, 100, 100, 100], vec![200, 200, 200, 200, 200]],
-            shape_id_grid
-        );
-    }
-
-    #[test]
-    fn can_calculate_the_number_of_top_level_possibilities() {
-        let piece = piece_from_name(0, PentominoName::I);
-        let transforms = piece.all_transforms();
-        let board = create_board(12, 5);
 
-        assert_eq!(18, board.number_of_top_level_possibilities(&transforms))
+        assert!(board.has_dead_region(&[&remaining_u, &remaining_u]));
+        assert!(!board.has_dead_region(&[&remaining_i, &remaining_i]));
     }
 
     #[test]
-    fn can_check_empty_space_is_multiple_of_five() {
-        let piece = piece_from_name(0, PentominoName::I);
-        let mut board = create_board(12, 5);
+    fn detects_region_too_narrow_for_remaining_pieces() {
+        let i_piece = piece_from_name(0, PentominoName::I).rotate_clockwise();
+        let remaining_i = piece_from_name(1, PentominoName::I).all_transforms();
+        let remaining_u = piece_from_name(2, PentominoName::U).all_transforms();
+        let mut board = create_board(2, 10);
         board.try_add(Placement {
             row: 0,
             column: 0,
-            piece: &piece,
+            piece: &i_piece,
+        });
+        board.try_add(Placement {
+            row: 5,
+            column: 0,
+            piece: &i_piece,
         });
 
-        assert!(board.empty_spaces_multiple_of_five());
+        assert!(board.has_dead_region(&[&remaining_u, &remaining_u]));
+        assert!(!board.has_dead_region(&[&remaining_i, &remaining_i]));
     }
 
     #[test]
@@ -427,7 +2269,7 @@ mod tests {
             piece_from_name(10, PentominoName::W).rotate_clockwise(), // 11 vec!["..*", ".**", "**."]
             piece_from_name(1, PentominoName::X),                     // 12
         ];
-        let placements: Vec<Placement> = vec![
+        let placements: Vec<Placement> = [
             (0, 0), //  1
             (3, 0), //  2
             (3, 5), //  3
@@ -446,7 +2288,7 @@ mod tests {
         .map(|((r, c), piece)| Placement {
             row: *r,
             column: *c,
-            piece: piece,
+            piece,
         })
         .collect();
 
@@ -467,22 +2309,335 @@ mod tests {
 
     #[test]
     fn can_find_unique_solutions() {
-        let pieces: Vec<Vec<Piece>> = vec![
-            piece_from_name(1, PentominoName::U),
-            piece_from_name(2, PentominoName::U),
-            piece_from_name(3, PentominoName::X),
-            piece_from_name(4, PentominoName::I),
-        ]
-        .iter()
-        .map(Piece::all_transforms)
-        .collect();
-        let pieces = Arc::new(pieces);
+        let pieces = u_u_x_i_transforms();
         let mut board = create_board(5, 4);
 
-        let solutions = board.find_solutions(&pieces);
+        let solutions = board
+            .find_solutions(&pieces, Branching::PieceOrder)
+            .solutions;
         assert_eq!(1, solutions.len());
     }
 
+    #[test]
+    fn one_sided_pieces_keep_their_handedness() {
+        let two_p = Arc::new(vec![
+            one_sided_piece_from_name(1, PentominoName::P).all_transforms(),
+            one_sided_piece_from_name(2, PentominoName::P).all_transforms(),
+        ]);
+        let p_and_mirror = Arc::new(vec![
+            one_sided_piece_from_name(1, PentominoName::P).all_transforms(),
+            mirrored_piece_from_name(2, PentominoName::P).all_transforms(),
+        ]);
+
+        let mut solutions = create_board(5, 2)
+            .find_solutions(&two_p, Branching::PieceOrder)
+            .solutions;
+        solutions.sort();
+        solutions.dedup();
+
+        assert_eq!(vec!["PPPPP PPPPP"], solutions);
+        assert!(create_board(5, 2)
+            .find_solutions(&p_and_mirror, Branching::PieceOrder)
+            .solutions
+            .is_empty());
+    }
+
+    #[test]
+    fn places_each_arrangement_of_repeated_pieces_once() {
+        let transforms = Arc::new(vec![piece_from_name(1, PentominoName::P).all_transforms()]);
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let solutions = create_board(5, 2)
+                .find_solutions_with_supply(&transforms, &[Supply::Exactly(2)], branching)
+                .solutions;
+
+            assert_eq!(vec!["PPPPP PPPPP"; 2], solutions, "{:?}", branching);
+        }
+    }
+
+    #[test]
+    fn can_tile_with_unlimited_pieces() {
+        let transforms = Arc::new(vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::X).all_transforms(),
+        ]);
+        let supply = [Supply::Unlimited, Supply::Exactly(0)];
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let mut solutions = create_board(5, 5)
+                .find_solutions_with_supply(&transforms, &supply, branching)
+                .solutions;
+            solutions.sort();
+
+            assert_eq!(
+                vec!["IIIII IIIII IIIII IIIII IIIII"; 2],
+                solutions,
+                "{:?}",
+                branching
+            );
+        }
+    }
+
+    #[test]
+    fn picks_subsets_of_a_larger_pool() {
+        let transforms = Arc::new(
+            [
+                PentominoName::I,
+                PentominoName::I,
+                PentominoName::L,
+                PentominoName::P,
+                PentominoName::P,
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| piece_from_name(i as i32, *name).all_transforms())
+            .collect::<Vec<_>>(),
+        );
+        let supply = [Supply::AtMost(1); 5];
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let mut subsets: Vec<String> = create_board(5, 2)
+                .find_solutions_with_supply(&transforms, &supply, branching)
+                .solutions
+                .iter()
+                .map(|s| piece_names(s))
+                .collect();
+            subsets.sort();
+            subsets.dedup();
+
+            assert_eq!(vec!["II", "PP"], subsets, "{:?}", branching);
+        }
+    }
+
+    #[test]
+    fn unlimited_pieces_must_fill_the_board() {
+        let transforms = Arc::new(vec![piece_from_name(1, PentominoName::U).all_transforms()]);
+
+        assert!(create_board(5, 2)
+            .find_solutions_with_supply(&transforms, &[Supply::Unlimited], Branching::PieceOrder)
+            .solutions
+            .is_empty());
+    }
+
+    #[test]
+    fn collects_search_statistics() {
+        let pieces = u_u_x_i_transforms();
+        let mut board = create_board(5, 4);
+
+        let result = board.find_solutions(&pieces, Branching::PieceOrder);
+        let statistics = result.statistics;
+
+        assert_eq!(5, statistics.nodes_per_depth.len());
+        assert_eq!(1, statistics.nodes_per_depth[0]);
+        assert_eq!(result.solutions.len() as u64, statistics.nodes_per_depth[4]);
+        assert_eq!(
+            result.solutions.len(),
+            statistics.solutions_per_subtree.iter().sum::<usize>()
+        );
+        assert_eq!(
+            statistics.solutions_per_subtree.len(),
+            statistics.thread_times.len()
+        );
+        assert!(statistics.placements_rejected > 0);
+        assert!(statistics.placements_tried > statistics.placements_rejected);
+    }
+
+    #[test]
+    fn most_constrained_cell_finds_all_orientations() {
+        let pieces = u_u_x_i_transforms();
+        let mut board = create_board(5, 4);
+
+        let mut solutions = board
+            .find_solutions(&pieces, Branching::MostConstrainedCell)
+            .solutions;
+        solutions.sort();
+        solutions.dedup();
+
+        assert_eq!(
+            vec!["IIIII UUXUU UXXXU UUXUU", "UUXUU UXXXU UUXUU IIIII"],
+            solutions
+        );
+    }
+
+    #[test]
+    fn forbidden_cells_rule_out_solutions() {
+        let pieces = u_u_x_i_transforms();
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let mut board = create_board(5, 4);
+            board.constraints = Constraints {
+                forbidden: vec![(4, 0, 0)],
+                ..Default::default()
+            };
+
+            let mut solutions = board.find_solutions(&pieces, branching).solutions;
+            solutions.sort();
+            solutions.dedup();
+
+            assert_eq!(vec!["UUXUU UXXXU UUXUU IIIII"], solutions);
+        }
+    }
+
+    #[test]
+    fn fixed_pieces_are_placed_before_searching() {
+        let pieces = u_u_x_i_transforms();
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let mut board = create_board(5, 4);
+            board.constraints = Constraints {
+                fixed: vec![FixedPiece {
+                    piece_id: 4,
+                    transform: 0,
+                    row: 3,
+                    column: 0,
+                }],
+                ..Default::default()
+            };
+
+            let mut solutions = board.find_solutions(&pieces, branching).solutions;
+            solutions.sort();
+            solutions.dedup();
+
+            assert_eq!(vec!["UUXUU UXXXU UUXUU IIIII"], solutions);
+            assert!(board.placements.is_empty());
+        }
+    }
+
+    #[test]
+    fn fixed_pieces_that_finish_the_board_are_the_solution() {
+        let pieces = Arc::new(vec![piece_from_name(1, PentominoName::I).all_transforms()]);
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            let mut board = create_board(5, 1);
+            board.constraints = Constraints {
+                fixed: vec![FixedPiece {
+                    piece_id: 1,
+                    transform: 0,
+                    row: 0,
+                    column: 0,
+                }],
+                ..Default::default()
+            };
+
+            let result =
+                board.find_solutions_with_supply(&pieces, &[Supply::Exactly(1)], branching);
+            assert_eq!(vec!["IIIII"], result.solutions);
+            assert!(result.complete);
+            assert!(board.placements.is_empty());
+        }
+    }
+
+    #[test]
+    fn fixed_pieces_that_do_not_fit_leave_no_solutions() {
+        let pieces = u_u_x_i_transforms();
+        let mut board = create_board(5, 4);
+        board.constraints = Constraints {
+            fixed: vec![FixedPiece {
+                piece_id: 4,
+                transform: 0,
+                row: 0,
+                column: 1,
+            }],
+            ..Default::default()
+        };
+
+        let result = board.find_solutions(&pieces, Branching::MostConstrainedCell);
+
+        assert!(result.solutions.is_empty());
+        assert!(board.placements.is_empty());
+    }
+
+    #[test]
+    fn clues_decide_whether_a_puzzle_is_unique() {
+        let pieces = u_u_x_i_transforms();
+        let supply = [Supply::Exactly(1); 4];
+        let with_clues = |clues| {
+            let mut board = create_board(5, 4);
+            board.constraints = Constraints {
+                clues,
+                ..Default::default()
+            };
+            board
+        };
+
+        assert_eq!(
+            Uniqueness::Unique("IIIII UUXUU UXXXU UUXUU".to_string()),
+            with_clues(vec![(PentominoName::I, 0, 2)]).check_uniqueness(
+                &pieces,
+                &supply,
+                Branching::MostConstrainedCell
+            )
+        );
+        assert_eq!(
+            Uniqueness::Ambiguous(vec![
+                "IIIII UUXUU UXXXU UUXUU".to_string(),
+                "UUXUU UXXXU UUXUU IIIII".to_string()
+            ]),
+            with_clues(vec![(PentominoName::U, 1, 0)]).check_uniqueness(
+                &pieces,
+                &supply,
+                Branching::PieceOrder
+            )
+        );
+        assert_eq!(
+            Uniqueness::NoSolution,
+            with_clues(vec![(PentominoName::X, 0, 0)]).check_uniqueness(
+                &pieces,
+                &supply,
+                Branching::MostConstrainedCell
+            )
+        );
+    }
+
+    #[test]
+    fn puzzles_without_clues_are_ambiguous_with_either_branching() {
+        let pieces = u_u_x_i_transforms();
+        let supply = [Supply::Exactly(1); 4];
+
+        for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+            assert_eq!(
+                Uniqueness::Ambiguous(vec![
+                    "IIIII UUXUU UXXXU UUXUU".to_string(),
+                    "UUXUU UXXXU UUXUU IIIII".to_string()
+                ]),
+                create_board(5, 4).check_uniqueness(&pieces, &supply, branching)
+            );
+        }
+    }
+
+    #[test]
+    fn piece_order_lists_every_solution_without_the_symmetry_cut() {
+        let pieces = u_u_x_i_transforms();
+        let mut board = create_board(5, 4);
+        board.set_symmetry_cut(false);
+
+        let mut piece_order = board
+            .find_solutions(&pieces, Branching::PieceOrder)
+            .solutions;
+        let mut most_constrained = create_board(5, 4)
+            .find_solutions(&pieces, Branching::MostConstrainedCell)
+            .solutions;
+        piece_order.sort();
+        most_constrained.sort();
+
+        assert_eq!(most_constrained, piece_order);
+    }
+
+    #[test]
+    fn most_constrained_cell_prefers_cell_with_fewest_placements() {
+        let transforms = vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::X).all_transforms(),
+        ];
+        let board = create_board(5, 4);
+
+        let moves = board.most_constrained_moves(&transforms, &[Supply::Exactly(1); 2]);
+
+        // X can't reach the top left corner and the vertical I doesn't fit
+        assert_eq!(1, moves.len());
+        assert_eq!((0, 0, 0), (moves[0].piece, moves[0].row, moves[0].column));
+    }
+
     #[test]
     fn generates_expected_name_grid() {
         let u_piece = piece_from_name(1, PentominoName::U);
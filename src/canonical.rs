@@ -0,0 +1,244 @@
+use crate::board::Topology;
+
+/// Picks a single representative for a solution string and its reflections so that solutions
+/// which are mirror images or rotations of each other compare equal.
+pub fn canonicalise_solution_string(solution: &str) -> String {
+    canonicalise(solution, |c| c, Topology::Rectangle)
+}
+
+/// As `canonicalise_solution_string` for one-sided pieces, where reflecting a solution also
+/// swaps each chiral piece for its mirror image, written in the other case.
+pub fn canonicalise_one_sided_solution_string(solution: &str) -> String {
+    canonicalise(solution, mirror_one_sided, Topology::Rectangle)
+}
+
+/// As `canonicalise_solution_string` for a square board, where solutions turned a quarter turn
+/// also compare equal.
+pub fn canonicalise_square_solution_string(solution: &str) -> String {
+    let rows: Vec<Vec<char>> = solution
+        .split_whitespace()
+        .map(|row| row.chars().collect())
+        .collect();
+    let transposed: Vec<String> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column]).collect())
+        .collect();
+
+    canonicalise_solution_string(solution).min(canonicalise_solution_string(&transposed.join(" ")))
+}
+
+/// As `canonicalise_solution_string` on a board whose edges may be joined, where solutions
+/// shifted around a joined edge also compare equal.
+pub fn canonicalise_wrapped_solution_string(
+    solution: &str,
+    topology: Topology,
+    one_sided: bool,
+) -> String {
+    if one_sided {
+        canonicalise(solution, mirror_one_sided, topology)
+    } else {
+        canonicalise(solution, |c| c, topology)
+    }
+}
+
+/// Picks a single representative for a box solution written by `Cuboid::layers` and every
+/// rotation or reflection of it that leaves the box in place.
+pub fn canonicalise_layered_solution_string(solution: &str) -> String {
+    let grid: Vec<Vec<Vec<char>>> = solution
+        .split(" / ")
+        .map(|layer| {
+            layer
+                .split_whitespace()
+                .map(|row| row.chars().collect())
+                .collect()
+        })
+        .collect();
+    let size = [grid.len(), grid[0].len(), grid[0][0].len()];
+    let mut best: Option<String> = None;
+
+    for axes in [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ] {
+        if (0..3).any(|i| size[axes[i]] != size[i]) {
+            continue;
+        }
+
+        for flips in 0..8 {
+            let source = |target: [usize; 3]| {
+                let mut cell = [0; 3];
+                for i in 0..3 {
+                    let along = if flips & (1 << i) != 0 {
+                        size[i] - 1 - target[i]
+                    } else {
+                        target[i]
+                    };
+                    cell[axes[i]] = along;
+                }
+                grid[cell[0]][cell[1]][cell[2]]
+            };
+            let layers: Vec<String> = (0..size[0])
+                .map(|layer| {
+                    let rows: Vec<String> = (0..size[1])
+                        .map(|row| {
+                            (0..size[2])
+                                .map(|column| source([layer, row, column]))
+                                .collect()
+                        })
+                        .collect();
+                    rows.join(" ")
+                })
+                .collect();
+            let candidate = layers.join(" / ");
+
+            if best.as_ref().is_none_or(|b| candidate < *b) {
+                best = Some(candidate);
+            }
+        }
+    }
+
+    best.unwrap()
+}
+
+fn mirror_one_sided(c: char) -> char {
+    if "FLNPYZflnpyz".contains(c) {
+        if c.is_ascii_uppercase() {
+            c.to_ascii_lowercase()
+        } else {
+            c.to_ascii_uppercase()
+        }
+    } else {
+        c
+    }
+}
+
+fn canonicalise(solution: &str, mirror: fn(char) -> char, topology: Topology) -> String {
+    let flip_horizontally = |solution: &[String]| -> Vec<String> {
+        solution
+            .iter()
+            .map(|s| s.chars().rev().map(mirror).collect())
+            .collect()
+    };
+
+    fn rotations(solution: &[String]) -> Vec<Vec<String>> {
+        // currently only does a 180 flip as focusing on rectangle cases
+        vec![solution
+            .iter()
+            .map(|s| s.chars().rev().collect())
+            .rev()
+            .collect()]
+    }
+
+    // Every way of moving the rows and columns around the joined edges.
+    fn shifts(solution: &[String], topology: Topology) -> Vec<Vec<String>> {
+        let height = solution.len();
+        let width = solution[0].chars().count();
+        let row_shifts = if topology.wraps_rows() { height } else { 1 };
+        let column_shifts = if topology.wraps_columns() { width } else { 1 };
+        let mut result = vec![];
+
+        for row_shift in 0..row_shifts {
+            for column_shift in 0..column_shifts {
+                result.push(
+                    (0..height)
+                        .map(|row| {
+                            let line: Vec<char> =
+                                solution[(row + row_shift) % height].chars().collect();
+                            (0..width)
+                                .map(|column| line[(column + column_shift) % width])
+                                .collect()
+                        })
+                        .collect(),
+                );
+            }
+        }
+
+        result
+    }
+
+    let input_solution: Vec<String> = solution.split_whitespace().map(|s| s.to_string()).collect();
+    let flipped_solution = flip_horizontally(&input_solution);
+    let mut candidates = rotations(&input_solution);
+    candidates.extend(rotations(&flipped_solution));
+    candidates.push(input_solution);
+    candidates.push(flipped_solution);
+
+    candidates
+        .iter()
+        .flat_map(|candidate| shifts(candidate, topology))
+        .min()
+        .unwrap()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Topology;
+    use crate::canonical::{
+        canonicalise_layered_solution_string, canonicalise_one_sided_solution_string,
+        canonicalise_solution_string, canonicalise_square_solution_string,
+        canonicalise_wrapped_solution_string,
+    };
+
+    #[test]
+    fn generates_expected_canonical_string() {
+        assert_eq!("AB CD", canonicalise_solution_string("AB CD"));
+        assert_eq!("AB CD", canonicalise_solution_string("BA DC"));
+        assert_eq!("AB CD", canonicalise_solution_string("DC BA"));
+        assert_eq!("AB CD", canonicalise_solution_string("CD AB"));
+        assert_eq!("ABC ADE", canonicalise_solution_string("ADE ABC"));
+    }
+
+    #[test]
+    fn square_boards_also_compare_quarter_turns() {
+        assert_eq!("AB CD", canonicalise_square_solution_string("CA DB"));
+        assert_eq!("AB CD", canonicalise_square_solution_string("BD AC"));
+        assert_eq!("AB CD", canonicalise_square_solution_string("AC BD"));
+        assert_eq!("AC BD", canonicalise_solution_string("CA DB"));
+    }
+
+    #[test]
+    fn reflections_swap_one_sided_pieces_for_their_mirror_images() {
+        assert_eq!("FI FI", canonicalise_one_sided_solution_string("IF IF"));
+        assert_eq!("FI FI", canonicalise_one_sided_solution_string("If If"));
+        assert_eq!("FI FI", canonicalise_one_sided_solution_string("fI fI"));
+    }
+
+    #[test]
+    fn compares_whole_solutions_not_just_the_first_row() {
+        assert_eq!("AA BC", canonicalise_solution_string("AA CB"));
+    }
+
+    #[test]
+    fn shifts_solutions_around_joined_edges() {
+        let cylinder = |s| canonicalise_wrapped_solution_string(s, Topology::Cylinder, false);
+        let torus = |s| canonicalise_wrapped_solution_string(s, Topology::Torus, false);
+
+        assert_eq!(cylinder("ABC DEF"), cylinder("CAB FDE"));
+        assert_ne!(cylinder("ABC DEF"), cylinder("ABC EFD"));
+        assert_eq!(torus("ABC DEF"), torus("FDE CAB"));
+        assert_eq!(
+            canonicalise_solution_string("BA DC"),
+            canonicalise_wrapped_solution_string("BA DC", Topology::Rectangle, false)
+        );
+    }
+
+    #[test]
+    fn turns_boxes_over() {
+        assert_eq!(
+            "AB CD / EF GH",
+            canonicalise_layered_solution_string("HG FE / DC BA")
+        );
+        assert_eq!(
+            canonicalise_layered_solution_string("AB / CD"),
+            canonicalise_layered_solution_string("DB / CA")
+        );
+        assert_ne!(
+            canonicalise_layered_solution_string("ABC / DEF"),
+            canonicalise_layered_solution_string("ACB / DEF")
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use crate::board::{Board, Placement};
+use crate::pieces::PentominoName;
+
+/// A piece fixed at a position and orientation before the search starts. The transform is an
+/// index into the piece's `Piece::all_transforms`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedPiece {
+    pub piece_id: i32,
+    pub transform: usize,
+    pub row: usize,
+    pub column: usize,
+}
+
+/// Puzzle rules on where individual pieces, identified by id, may go.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Constraints {
+    pub fixed: Vec<FixedPiece>,
+    /// Cells, as (piece id, row, column), that a piece may not cover.
+    pub forbidden: Vec<(i32, usize, usize)>,
+    /// Pieces that have to touch the edge of the board.
+    pub touch_border: Vec<i32>,
+    /// Cells, as (name, row, column), that can only be covered by a piece with that name.
+    pub clues: Vec<(PentominoName, usize, usize)>,
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty()
+            && self.forbidden.is_empty()
+            && self.touch_border.is_empty()
+            && self.clues.is_empty()
+    }
+
+    pub fn allows(&self, placement: &Placement, board: &Board) -> bool {
+        let piece = placement.piece;
+        let cells = board.covered_cells(placement);
+
+        if self.touch_border.contains(&piece.id)
+            && !cells
+                .iter()
+                .any(|(row, column)| board.on_border(*row, *column))
+        {
+            return false;
+        }
+
+        !self.forbidden.iter().any(|(piece_id, row, column)| {
+            *piece_id == piece.id && cells.contains(&(*row, *column))
+        }) && !self
+            .clues
+            .iter()
+            .any(|(name, row, column)| *name != piece.name && cells.contains(&(*row, *column)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{create_board, Placement};
+    use crate::constraints::Constraints;
+    use crate::pieces::{piece_from_name, PentominoName};
+
+    #[test]
+    fn allows_anything_without_constraints() {
+        let board = create_board(5, 5);
+        let piece = piece_from_name(1, PentominoName::X);
+        let constraints = Constraints::default();
+
+        assert!(constraints.is_empty());
+        assert!(constraints.allows(
+            &Placement {
+                row: 1,
+                column: 1,
+                piece: &piece
+            },
+            &board
+        ));
+    }
+
+    #[test]
+    fn forbids_solid_cells_only() {
+        let board = create_board(5, 5);
+        let piece = piece_from_name(1, PentominoName::X);
+        let forbidding = |piece_id, row, column| Constraints {
+            forbidden: vec![(piece_id, row, column)],
+            ..Default::default()
+        };
+        let placement = Placement {
+            row: 0,
+            column: 0,
+            piece: &piece,
+        };
+
+        assert!(forbidding(1, 0, 0).allows(&placement, &board));
+        assert!(!forbidding(1, 0, 1).allows(&placement, &board));
+        assert!(forbidding(2, 0, 1).allows(&placement, &board));
+        assert!(forbidding(1, 3, 3).allows(&placement, &board));
+    }
+
+    #[test]
+    fn requires_border_pieces_to_touch_an_edge() {
+        let board = create_board(5, 5);
+        let piece = piece_from_name(1, PentominoName::X);
+        let constraints = Constraints {
+            touch_border: vec![1],
+            ..Default::default()
+        };
+        let placement = |row, column| Placement {
+            row,
+            column,
+            piece: &piece,
+        };
+
+        assert!(!constraints.allows(&placement(1, 1), &board));
+        assert!(constraints.allows(&placement(0, 1), &board));
+        assert!(constraints.allows(&placement(2, 1), &board));
+        assert!(constraints.allows(&placement(1, 2), &board));
+    }
+
+    #[test]
+    fn clue_cells_only_take_the_named_piece() {
+        let board = create_board(5, 5);
+        let x_piece = piece_from_name(1, PentominoName::X);
+        let i_piece = piece_from_name(2, PentominoName::I);
+        let constraints = Constraints {
+            clues: vec![(PentominoName::X, 1, 1)],
+            ..Default::default()
+        };
+        let placement = |row, column, piece| Placement { row, column, piece };
+
+        assert!(constraints.allows(&placement(0, 0, &x_piece), &board));
+        assert!(!constraints.allows(&placement(1, 0, &i_piece), &board));
+        assert!(constraints.allows(&placement(0, 0, &i_piece), &board));
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::board::{search_subtrees, SearchResult, Supply};
+use crate::interrupt::{stopped, INTERRUPTED};
+use crate::report::{report, Reporter, SearchEvent};
+use crate::stats::SearchStatistics;
+
+/// One position and orientation of a piece from a group, as the indices of the cells it
+/// covers.
+#[derive(Clone, Debug)]
+pub(crate) struct CoverPlacement {
+    pub(crate) group: usize,
+    pub(crate) name: char,
+    pub(crate) cells: Vec<usize>,
+}
+
+/// How a fill is run, as opposed to what it fills: the flag that stops it early, how many
+/// threads it may use as for `Board::set_threads`, and who hears about each solution.
+#[derive(Clone, Debug)]
+pub(crate) struct FillControls {
+    pub(crate) stop: &'static AtomicBool,
+    pub(crate) threads: usize,
+    pub(crate) reporter: Option<Reporter>,
+}
+
+impl Default for FillControls {
+    fn default() -> Self {
+        FillControls {
+            stop: &INTERRUPTED,
+            threads: 0,
+            reporter: None,
+        }
+    }
+}
+
+// What the search threads share: the order to fill cells in, the placements with their cells
+// sorted into that order, each placement listed under the cell it fills first, and how to
+// write out a solution.
+struct CoverSearch<R> {
+    order: Vec<usize>,
+    placements: Vec<CoverPlacement>,
+    by_first_cell: Vec<Vec<usize>>,
+    render: R,
+    stop: &'static AtomicBool,
+    reporter: Option<Reporter>,
+}
+
+fn fits(cells: &[char], placement: &CoverPlacement) -> bool {
+    placement.cells.iter().all(|i| cells[*i] == '.')
+}
+
+fn set_cells(cells: &mut [char], placement: &CoverPlacement, name: char) {
+    for i in placement.cells.iter() {
+        cells[*i] = name;
+    }
+}
+
+// The first empty cell in the fill order has to be covered by the first cell of some
+// placement, so only those are tried.
+fn place_from_first_empty<R: Fn(&[char]) -> String>(
+    cells: &mut [char],
+    search: &CoverSearch<R>,
+    depth: usize,
+    supply: &mut [Supply],
+    statistics: &mut SearchStatistics,
+) -> Vec<String> {
+    let first_empty = match search.order.iter().find(|i| cells[**i] == '.') {
+        Some(first_empty) => *first_empty,
+        None if !supply.iter().any(Supply::required) => {
+            let solution = (search.render)(cells);
+            report(&search.reporter, &SearchEvent::Solution(&solution));
+            return vec![solution];
+        }
+        None => return vec![],
+    };
+
+    let mut solutions = vec![];
+    for placement in search.by_first_cell[first_empty]
+        .iter()
+        .map(|p| &search.placements[*p])
+    {
+        if stopped(search.stop) {
+            break;
+        }
+        if !supply[placement.group].available() {
+            continue;
+        }
+
+        let added = fits(cells, placement);
+        statistics.record_placement(added);
+        if added {
+            statistics.record_node(depth + 1);
+            set_cells(cells, placement, placement.name);
+            supply[placement.group].take();
+            let mut child_solutions =
+                place_from_first_empty(cells, search, depth + 1, supply, statistics);
+            solutions.append(&mut child_solutions);
+            supply[placement.group].give_back();
+            set_cells(cells, placement, '.');
+        }
+    }
+
+    solutions
+}
+
+/// Fills every empty ('.') cell with the given placements, using each group as many times as
+/// its supply allows, and writes out each solution with `render`, reporting it as it is found.
+/// Cells are filled in `order`, with the work split into one search per placement covering the
+/// first empty cell, each on its own thread up to the thread limit, until the stop flag is set.
+pub(crate) fn fill_cells<R>(
+    cells: &[char],
+    order: Vec<usize>,
+    mut placements: Vec<CoverPlacement>,
+    supply: &[Supply],
+    render: R,
+    controls: FillControls,
+) -> SearchResult
+where
+    R: Fn(&[char]) -> String + Send + Sync + 'static,
+{
+    let mut statistics = SearchStatistics::default();
+    statistics.record_node(0);
+
+    let mut rank = vec![0; cells.len()];
+    for (position, index) in order.iter().enumerate() {
+        rank[*index] = position;
+    }
+    let mut by_first_cell = vec![vec![]; cells.len()];
+    for (i, placement) in placements.iter_mut().enumerate() {
+        placement.cells.sort_unstable_by_key(|cell| rank[*cell]);
+        by_first_cell[placement.cells[0]].push(i);
+    }
+    let search = Arc::new(CoverSearch {
+        order,
+        placements,
+        by_first_cell,
+        render,
+        stop: controls.stop,
+        reporter: controls.reporter,
+    });
+
+    let first_empty = match search.order.iter().find(|i| cells[**i] == '.') {
+        Some(first_empty) => *first_empty,
+        None => {
+            return SearchResult {
+                solutions: vec![],
+                statistics,
+                complete: true,
+            }
+        }
+    };
+
+    let mut solutions = vec![];
+    let subtrees = search.by_first_cell[first_empty].clone();
+    let subtrees = subtrees.into_iter().map(|top_level| {
+        let mut child_cells = cells.to_vec();
+        let child_search = Arc::clone(&search);
+        let mut child_supply = supply.to_vec();
+
+        move || {
+            let start = Instant::now();
+            let mut statistics = SearchStatistics::default();
+            let placement = &child_search.placements[top_level];
+
+            let added = child_supply[placement.group].available() && fits(&child_cells, placement);
+            statistics.record_placement(added);
+            let solutions = if added {
+                statistics.record_node(1);
+                set_cells(&mut child_cells, placement, placement.name);
+                child_supply[placement.group].take();
+                place_from_first_empty(
+                    &mut child_cells,
+                    &child_search,
+                    1,
+                    &mut child_supply,
+                    &mut statistics,
+                )
+            } else {
+                vec![]
+            };
+
+            statistics.solutions_per_subtree.push(solutions.len());
+            statistics.thread_times.push(start.elapsed());
+            (solutions, statistics)
+        }
+    });
+    search_subtrees(
+        subtrees,
+        controls.threads,
+        |child_solutions, child_statistics| {
+            solutions.extend_from_slice(&child_solutions);
+            statistics.merge(&child_statistics);
+        },
+    );
+    SearchResult {
+        solutions,
+        statistics,
+        complete: !stopped(controls.stop),
+    }
+}
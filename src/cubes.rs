@@ -0,0 +1,465 @@
+use std::collections::{BTreeSet, HashSet};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::board::{SearchResult, Supply};
+use crate::cover::{fill_cells, CoverPlacement, FillControls};
+use crate::pieces::{piece_from_name, PentominoName, Piece};
+use crate::report::Reporter;
+
+/// A cell of a polycube or box as (layer, row, column).
+pub type Cell = (u8, u8, u8);
+
+// A cell relative to another while turning a polycube, which may be negative.
+type Offset = (i32, i32, i32);
+
+/// A piece made of cubes joined face to face, which can be turned any way in space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polycube {
+    pub id: i32,
+    pub name: char,
+    /// The cubes, moved as close to the origin as possible and sorted.
+    pub cells: Vec<Cell>,
+    /// One-sided polycubes can't be reflected, so only their rotations are used.
+    pub one_sided: bool,
+}
+
+fn normalise(cells: &[Offset]) -> Vec<Cell> {
+    let min_layer = cells.iter().map(|c| c.0).min().unwrap_or(0);
+    let min_row = cells.iter().map(|c| c.1).min().unwrap_or(0);
+    let min_column = cells.iter().map(|c| c.2).min().unwrap_or(0);
+    let mut result: Vec<Cell> = cells
+        .iter()
+        .map(|(l, r, c)| {
+            (
+                (l - min_layer) as u8,
+                (r - min_row) as u8,
+                (c - min_column) as u8,
+            )
+        })
+        .collect();
+    result.sort_unstable();
+
+    result
+}
+
+fn signed(cells: &[Cell]) -> Vec<Offset> {
+    cells
+        .iter()
+        .map(|(l, r, c)| (i32::from(*l), i32::from(*r), i32::from(*c)))
+        .collect()
+}
+
+// The 24 rotations are generated by quarter turns about the column and layer axes.
+fn rotated_cell_sets(cells: &[Cell]) -> Vec<Vec<Cell>> {
+    let turns: [fn(Offset) -> Offset; 2] = [|(l, r, c)| (r, -l, c), |(l, r, c)| (l, c, -r)];
+    let mut seen = HashSet::new();
+    let mut pending = vec![cells.to_vec()];
+    let mut result = vec![];
+
+    while let Some(current) = pending.pop() {
+        if !seen.insert(current.clone()) {
+            continue;
+        }
+
+        for turn in turns {
+            let turned: Vec<Offset> = signed(&current).into_iter().map(turn).collect();
+            pending.push(normalise(&turned));
+        }
+        result.push(current);
+    }
+    result.sort();
+
+    result
+}
+
+fn reflected(cells: &[Cell]) -> Vec<Cell> {
+    let mirrored: Vec<Offset> = signed(cells)
+        .into_iter()
+        .map(|(l, r, c)| (l, r, -c))
+        .collect();
+
+    normalise(&mirrored)
+}
+
+// The smallest cell set among every rotation and reflection, which is the same for any two
+// polycubes that can be turned into each other.
+fn free_canonical(cells: &[Cell]) -> Vec<Cell> {
+    rotated_cell_sets(cells)
+        .into_iter()
+        .chain(rotated_cell_sets(&reflected(cells)))
+        .min()
+        .unwrap()
+}
+
+impl Polycube {
+    /// The (depth, height, width) of the box around the polycube.
+    pub fn size(&self) -> (u8, u8, u8) {
+        let extent = |f: fn(&Cell) -> u8| self.cells.iter().map(f).max().map_or(0, |m| m + 1);
+
+        (extent(|c| c.0), extent(|c| c.1), extent(|c| c.2))
+    }
+
+    /// The distinct rotations of this polycube, at most 24.
+    pub fn rotations(&self) -> Vec<Polycube> {
+        rotated_cell_sets(&self.cells)
+            .into_iter()
+            .map(|cells| Polycube {
+                cells,
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// The distinct rotations and, unless the polycube is one-sided, reflections, at most 48.
+    pub fn all_orientations(&self) -> Vec<Polycube> {
+        let mut cell_sets: BTreeSet<Vec<Cell>> =
+            rotated_cell_sets(&self.cells).into_iter().collect();
+        if !self.one_sided {
+            cell_sets.extend(rotated_cell_sets(&reflected(&self.cells)));
+        }
+
+        cell_sets
+            .into_iter()
+            .map(|cells| Polycube {
+                cells,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+/// A flat piece as a polycube one layer deep.
+pub fn polycube_from_piece(piece: &Piece) -> Polycube {
+    let cells = (0..piece.height)
+        .flat_map(|row| (0..piece.width).map(move |column| (row, column)))
+        .filter(|(row, column)| piece.is_solid(*row, *column))
+        .map(|(row, column)| (0, row, column))
+        .collect();
+
+    Polycube {
+        id: piece.id,
+        name: piece.name_char(),
+        cells,
+        one_sided: piece.one_sided,
+    }
+}
+
+/// The 23 pentacubes counting reflections as the same piece: the twelve flat pentominoes,
+/// named as usual, followed by the eleven that aren't flat. Those have no standard letters, so
+/// take A, B, C, D, E, G, H, J, K, M and O in the order they are enumerated.
+pub fn pentacubes() -> Vec<Polycube> {
+    use PentominoName::*;
+
+    // Grow every polycube one cube at a time, keeping one of each shape.
+    let mut shapes: BTreeSet<Vec<Cell>> = [vec![(1, 1, 1)]].into_iter().collect();
+    for _ in 1..5 {
+        let mut grown = BTreeSet::new();
+        for shape in shapes.iter() {
+            for (l, r, c) in signed(shape) {
+                for (dl, dr, dc) in [
+                    (1, 0, 0),
+                    (-1, 0, 0),
+                    (0, 1, 0),
+                    (0, -1, 0),
+                    (0, 0, 1),
+                    (0, 0, -1),
+                ] {
+                    let mut cells = signed(shape);
+                    let added = (l + dl, r + dr, c + dc);
+                    if !cells.contains(&added) {
+                        cells.push(added);
+                        grown.insert(free_canonical(&normalise(&cells)));
+                    }
+                }
+            }
+        }
+        shapes = grown;
+    }
+
+    let flat: Vec<Polycube> = [F, I, L, N, P, T, U, V, W, X, Y, Z]
+        .into_iter()
+        .map(|name| polycube_from_piece(&piece_from_name(0, name)))
+        .collect();
+    let flat_shapes: Vec<Vec<Cell>> = flat.iter().map(|p| free_canonical(&p.cells)).collect();
+    let solid = shapes
+        .into_iter()
+        .filter(|shape| !flat_shapes.contains(shape))
+        .zip("ABCDEGHJKMO".chars())
+        .map(|(cells, name)| Polycube {
+            id: 0,
+            name,
+            cells,
+            one_sided: false,
+        });
+
+    flat.into_iter()
+        .chain(solid)
+        .enumerate()
+        .map(|(i, polycube)| Polycube {
+            id: i as i32 + 1,
+            ..polycube
+        })
+        .collect()
+}
+
+/// A box to fill with polycubes, holding the name of the piece in each cell or '.' when empty.
+#[derive(Clone, Debug)]
+pub struct Cuboid {
+    width: u8,
+    height: u8,
+    depth: u8,
+    cells: Vec<char>,
+    controls: FillControls,
+}
+
+fn layers(width: u8, height: u8, cells: &[char]) -> String {
+    let width = usize::from(width);
+    let layer_size = width * usize::from(height);
+    let layers: Vec<String> = cells
+        .chunks(layer_size)
+        .map(|layer| {
+            let rows: Vec<String> = layer
+                .chunks(width)
+                .map(|row| row.iter().collect())
+                .collect();
+            rows.join(" ")
+        })
+        .collect();
+
+    layers.join(" / ")
+}
+
+pub fn create_cuboid(width: u8, height: u8, depth: u8) -> Cuboid {
+    Cuboid {
+        width,
+        height,
+        depth,
+        cells: vec!['.'; usize::from(width) * usize::from(height) * usize::from(depth)],
+        controls: FillControls::default(),
+    }
+}
+
+impl Cuboid {
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// The name in each cell, layer by layer from the bottom and row by row within a layer.
+    pub fn cells(&self) -> &[char] {
+        &self.cells
+    }
+
+    /// Stops the search early once `stop` is set, in place of the Ctrl-C flag.
+    pub fn set_stop_flag(&mut self, stop: &'static AtomicBool) {
+        self.controls.stop = stop;
+    }
+
+    /// Limits how many searches from the first empty cell run at once, as `Board::set_threads`
+    /// does for the board's first moves.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.controls.threads = threads;
+    }
+
+    /// Hears about each way of filling the box as it is found.
+    pub fn set_reporter(&mut self, reporter: Reporter) {
+        self.controls.reporter = Some(reporter);
+    }
+
+    fn index(&self, (layer, row, column): Cell) -> usize {
+        (usize::from(layer) * usize::from(self.height) + usize::from(row)) * usize::from(self.width)
+            + usize::from(column)
+    }
+
+    fn cells_at(&self, polycube: &Polycube, at: Cell) -> Option<Vec<usize>> {
+        let (depth, height, width) = polycube.size();
+        let fits = |start: u8, size: u8, length: u8| {
+            u16::from(start) + u16::from(size) <= u16::from(length)
+        };
+        if !fits(at.0, depth, self.depth)
+            || !fits(at.1, height, self.height)
+            || !fits(at.2, width, self.width)
+        {
+            return None;
+        }
+
+        Some(
+            polycube
+                .cells
+                .iter()
+                .map(|(l, r, c)| self.index((at.0 + l, at.1 + r, at.2 + c)))
+                .collect(),
+        )
+    }
+
+    /// Places a polycube with its box's corner at `at`, if it fits among the cubes already
+    /// placed.
+    pub fn try_add(&mut self, polycube: &Polycube, at: Cell) -> bool {
+        match self.cells_at(polycube, at) {
+            Some(cells) if cells.iter().all(|i| self.cells[*i] == '.') => {
+                for i in cells {
+                    self.cells[i] = polycube.name;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(|c| *c != '.')
+    }
+
+    /// The box layer by layer from the bottom, each written as rows of piece names separated by
+    /// spaces, with the layers separated by " / ".
+    pub fn layers(&self) -> String {
+        layers(self.width, self.height, &self.cells)
+    }
+
+    // Cells ordered with the longest side of the box changing slowest, so the search works
+    // through narrow cross sections one at a time.
+    fn fill_order(&self) -> Vec<usize> {
+        let sizes = [self.depth, self.height, self.width];
+        let mut axes = [0, 1, 2];
+        axes.sort_by_key(|axis| std::cmp::Reverse(sizes[*axis]));
+
+        let mut cells: Vec<Cell> = (0..self.depth)
+            .flat_map(|l| {
+                (0..self.height).flat_map(move |r| (0..self.width).map(move |c| (l, r, c)))
+            })
+            .collect();
+        cells.sort_by_key(|(l, r, c)| {
+            let coordinates = [*l, *r, *c];
+            axes.map(|axis| coordinates[axis])
+        });
+
+        cells.into_iter().map(|cell| self.index(cell)).collect()
+    }
+
+    fn all_placements(&self, orientations: &[Vec<Polycube>]) -> Vec<CoverPlacement> {
+        let mut placements = vec![];
+
+        for (group, group_orientations) in orientations.iter().enumerate() {
+            for orientation in group_orientations {
+                for layer in 0..self.depth {
+                    for row in 0..self.height {
+                        for column in 0..self.width {
+                            if let Some(cells) = self.cells_at(orientation, (layer, row, column)) {
+                                placements.push(CoverPlacement {
+                                    group,
+                                    name: orientation.name,
+                                    cells,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+
+    pub fn find_solutions(&mut self, orientations: &Arc<Vec<Vec<Polycube>>>) -> SearchResult {
+        self.find_solutions_with_supply(orientations, &vec![Supply::Exactly(1); orientations.len()])
+    }
+
+    /// Searches for ways to fill the box completely using each piece as many times as its
+    /// supply allows.
+    pub fn find_solutions_with_supply(
+        &mut self,
+        orientations: &Arc<Vec<Vec<Polycube>>>,
+        supply: &[Supply],
+    ) -> SearchResult {
+        let (width, height) = (self.width, self.height);
+
+        fill_cells(
+            &self.cells,
+            self.fill_order(),
+            self.all_placements(orientations),
+            supply,
+            move |cells| layers(width, height, cells),
+            self.controls.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canonical::canonicalise_layered_solution_string;
+    use crate::cubes::{create_cuboid, pentacubes, polycube_from_piece, Polycube};
+    use crate::pieces::{piece_from_name, standard_pieces, PentominoName};
+    use std::sync::Arc;
+
+    #[test]
+    fn generates_all_rotations_and_reflections() {
+        let l_piece = polycube_from_piece(&piece_from_name(1, PentominoName::L));
+        let x_piece = polycube_from_piece(&piece_from_name(2, PentominoName::X));
+
+        assert_eq!(24, l_piece.rotations().len());
+        assert_eq!(24, l_piece.all_orientations().len());
+        assert_eq!(3, x_piece.all_orientations().len());
+        assert_eq!((1, 2, 4), l_piece.size());
+    }
+
+    #[test]
+    fn enumerates_the_pentacubes() {
+        let pieces = pentacubes();
+        let chiral = pieces
+            .iter()
+            .filter(|p| p.rotations().len() != p.all_orientations().len())
+            .count();
+
+        assert_eq!(23, pieces.len());
+        assert_eq!('F', pieces[0].name);
+        assert_eq!('A', pieces[12].name);
+        assert_eq!(23, pieces.last().unwrap().id);
+        // Reflecting these gives the other six of the 29 one-sided pentacubes
+        assert_eq!(6, chiral);
+    }
+
+    #[test]
+    fn renders_layers() {
+        let mut cuboid = create_cuboid(2, 2, 2);
+        let piece = Polycube {
+            id: 1,
+            name: 'A',
+            cells: vec![(0, 0, 0), (1, 0, 0), (1, 0, 1)],
+            one_sided: false,
+        };
+
+        assert!(cuboid.try_add(&piece, (0, 1, 0)));
+        assert!(!cuboid.try_add(&piece, (0, 1, 1)));
+        assert_eq!(".. A. / .. AA", cuboid.layers());
+    }
+
+    #[test]
+    fn fills_2x3x10_box_with_flat_pentominoes() {
+        let orientations = Arc::new(
+            standard_pieces()
+                .iter()
+                .map(|piece| polycube_from_piece(piece).all_orientations())
+                .collect(),
+        );
+        let mut cuboid = create_cuboid(10, 3, 2);
+
+        let mut solutions: Vec<String> = cuboid
+            .find_solutions(&orientations)
+            .solutions
+            .iter()
+            .map(|s| canonicalise_layered_solution_string(s))
+            .collect();
+        solutions.sort();
+        solutions.dedup();
+
+        assert_eq!(12, solutions.len());
+    }
+}
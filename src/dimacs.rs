@@ -0,0 +1,301 @@
+//! Handing a board to an external SAT solver. Each placement that fits becomes a variable,
+//! every open cell has to be covered by exactly one placement and every piece placed as often
+//! as its supply says, which the solver's answer can then be read back as.
+
+use crate::board::{check_piece_counts, Board, Placement, Supply};
+use crate::error::Error;
+use crate::pieces::Piece;
+
+/// A board and its pieces as a boolean formula in conjunctive normal form, where variable `n`
+/// is true when `placements[n - 1]` is used.
+#[derive(Clone, Debug)]
+pub struct CnfEncoding<'a> {
+    /// The board with any fixed pieces placed, which solutions are built on.
+    pub board: Board<'a>,
+    pub placements: Vec<Placement<'a>>,
+    /// Clauses as lists of variables, negated when false is wanted.
+    pub clauses: Vec<Vec<i64>>,
+    // The piece set, the supply left after fixed pieces and each placement's group, for
+    // checking an answer's piece counts.
+    transforms: &'a [Vec<Piece>],
+    supply: Vec<Supply>,
+    groups: Vec<usize>,
+}
+
+fn at_most_one(variables: &[i64], clauses: &mut Vec<Vec<i64>>) {
+    for (i, first) in variables.iter().enumerate() {
+        for second in variables[i + 1..].iter() {
+            clauses.push(vec![-first, -second]);
+        }
+    }
+}
+
+/// Encodes filling every open cell of a board, honouring its constraints. Pieces with
+/// `Exactly(1)` are placed exactly once, `AtMost(1)` at most once and `Unlimited` any number of
+/// times; larger counts aren't supported.
+pub fn encode_cnf<'a>(
+    board: &Board<'a>,
+    transforms: &'a [Vec<Piece>],
+    supply: &[Supply],
+) -> Result<CnfEncoding<'a>, Error> {
+    if let Some(unsupported) = supply
+        .iter()
+        .find(|s| matches!(s, Supply::Exactly(n) | Supply::AtMost(n) if *n > 1))
+    {
+        return Err(Error::UnsupportedSupply(*unsupported));
+    }
+
+    // Fixed pieces go on the board first, as they do before searching.
+    let (board, remaining) = board.with_fixed_pieces(transforms, supply)?;
+    let (groups, placements): (Vec<usize>, Vec<Placement<'a>>) = board
+        .legal_placements(transforms, &remaining)
+        .into_iter()
+        .unzip();
+    let mut covering = vec![vec![]; board.filled.len()];
+    for (i, placement) in placements.iter().enumerate() {
+        for (row, column) in board.covered_cells(placement) {
+            covering[row * board.width + column].push(i as i64 + 1);
+        }
+    }
+
+    let mut clauses = vec![];
+    for (index, variables) in covering.iter().enumerate() {
+        if !board.filled[index] {
+            clauses.push(variables.clone());
+            at_most_one(variables, &mut clauses);
+        }
+    }
+    for (group, group_supply) in remaining.iter().enumerate() {
+        let variables: Vec<i64> = (0..placements.len())
+            .filter(|i| groups[*i] == group)
+            .map(|i| i as i64 + 1)
+            .collect();
+
+        if group_supply.required() {
+            clauses.push(variables.clone());
+        }
+        if *group_supply != Supply::Unlimited {
+            at_most_one(&variables, &mut clauses);
+        }
+    }
+
+    Ok(CnfEncoding {
+        board,
+        placements,
+        clauses,
+        transforms,
+        supply: remaining,
+        groups,
+    })
+}
+
+impl<'a> CnfEncoding<'a> {
+    /// The formula in the DIMACS CNF format read by SAT solvers, with a comment line naming the
+    /// piece and position behind each variable.
+    pub fn to_dimacs(&self) -> String {
+        let mut lines = vec![];
+
+        for (i, placement) in self.placements.iter().enumerate() {
+            lines.push(format!(
+                "c {} {} at row {} column {}",
+                i + 1,
+                placement.piece.name_char(),
+                placement.row,
+                placement.column
+            ));
+        }
+        lines.push(format!(
+            "p cnf {} {}",
+            self.placements.len(),
+            self.clauses.len()
+        ));
+        for clause in self.clauses.iter() {
+            let literals: Vec<String> = clause.iter().map(i64::to_string).collect();
+            lines.push(format!("{} 0", literals.join(" ")));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads a SAT solver's answer, either as bare literals or the competition format of "s" and
+    /// "v" lines, and places the placements it sets true on the board. An "s" line saying
+    /// anything but SATISFIABLE means there is no assignment to read, and an assignment placing
+    /// a piece more or less often than its supply allows is rejected.
+    pub fn board_from_assignment(&self, assignment: &str) -> Result<Board<'a>, Error> {
+        let mut board = self.board.clone();
+        let mut used = vec![];
+
+        for line in assignment.lines().map(str::trim) {
+            if line.starts_with('c') || line == "SAT" {
+                continue;
+            }
+            if line == "UNSAT" {
+                return Err(Error::Unsatisfiable);
+            }
+            if let Some(status) = line.strip_prefix('s') {
+                match status.trim() {
+                    "SATISFIABLE" => continue,
+                    "UNSATISFIABLE" => return Err(Error::Unsatisfiable),
+                    status => return Err(Error::NoAssignment(status.to_string())),
+                }
+            }
+
+            for literal in line.trim_start_matches('v').split_whitespace() {
+                let variable: i64 = literal
+                    .parse()
+                    .map_err(|_| Error::BadLiteral(literal.to_string()))?;
+                if variable <= 0 {
+                    continue;
+                }
+
+                let placement = self
+                    .placements
+                    .get(variable as usize - 1)
+                    .ok_or(Error::UnknownVariable(variable))?;
+                board.try_place(placement.clone())?;
+                used.push(self.groups[variable as usize - 1]);
+            }
+        }
+
+        if let Some(index) = board.filled.iter().position(|f| !f) {
+            return Err(Error::UncoveredCell {
+                row: index / board.width,
+                column: index % board.width,
+            });
+        }
+        check_piece_counts(self.transforms, &self.supply, used)?;
+
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{create_board, Branching, Supply};
+    use crate::dimacs::encode_cnf;
+    use crate::error::Error;
+    use crate::parse::board_from_name_grid;
+    use crate::pieces::{piece_from_name, PentominoName};
+    use crate::testing::{same_placement, u_u_x_i_transforms};
+
+    #[test]
+    fn solutions_satisfy_the_formula_and_read_back() {
+        let transforms = u_u_x_i_transforms();
+        let encoding = encode_cnf(&create_board(5, 4), &transforms, &[Supply::Exactly(1); 4]);
+        let encoding = encoding.unwrap();
+        let solutions = create_board(5, 4)
+            .find_solutions(&transforms, Branching::MostConstrainedCell)
+            .solutions;
+
+        for solution in solutions.iter() {
+            let solved = board_from_name_grid(solution, &transforms).unwrap();
+            let assignment: Vec<i64> = encoding
+                .placements
+                .iter()
+                .enumerate()
+                .map(|(i, placement)| {
+                    let used = solved
+                        .placements
+                        .iter()
+                        .any(|p| same_placement(p, placement));
+                    if used {
+                        i as i64 + 1
+                    } else {
+                        -(i as i64 + 1)
+                    }
+                })
+                .collect();
+            assert!(encoding
+                .clauses
+                .iter()
+                .all(|clause| clause.iter().any(|l| assignment.contains(l))));
+
+            let answer = format!(
+                "s SATISFIABLE\nv {} 0",
+                assignment
+                    .iter()
+                    .map(i64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+            let board = encoding.board_from_assignment(&answer).unwrap();
+            assert_eq!(*solution, board.name_grid());
+        }
+    }
+
+    #[test]
+    fn writes_dimacs_and_rejects_bad_answers() {
+        let transforms = vec![piece_from_name(1, PentominoName::I).all_transforms()];
+        let encoding = encode_cnf(&create_board(5, 1), &transforms, &[Supply::Exactly(1)]);
+        let encoding = encoding.unwrap();
+
+        assert_eq!(
+            "c 1 I at row 0 column 0\np cnf 1 6\n1 0\n1 0\n1 0\n1 0\n1 0\n1 0\n",
+            encoding.to_dimacs()
+        );
+        assert_eq!(
+            "IIIII",
+            encoding.board_from_assignment("1 0").unwrap().name_grid()
+        );
+        assert_eq!(
+            Some(Error::UncoveredCell { row: 0, column: 0 }),
+            encoding.board_from_assignment("-1 0").err()
+        );
+        assert_eq!(
+            Some(Error::Unsatisfiable),
+            encoding.board_from_assignment("s UNSATISFIABLE").err()
+        );
+        assert_eq!(
+            Some(Error::Unsatisfiable),
+            encoding.board_from_assignment("UNSAT").err()
+        );
+        assert_eq!(
+            Some(Error::NoAssignment("UNKNOWN".to_string())),
+            encoding.board_from_assignment("s UNKNOWN\nv 1 0").err()
+        );
+        assert_eq!(
+            "IIIII",
+            encoding
+                .board_from_assignment("s SATISFIABLE\nv 1 0")
+                .unwrap()
+                .name_grid()
+        );
+        assert_eq!(
+            Some(Error::UnknownVariable(2)),
+            encoding.board_from_assignment("v 2 0").err()
+        );
+        assert_eq!(
+            Some(Error::UnsupportedSupply(Supply::Exactly(2))),
+            encode_cnf(&create_board(5, 2), &transforms, &[Supply::Exactly(2)]).err()
+        );
+    }
+
+    #[test]
+    fn rejects_answers_that_place_a_piece_twice() {
+        let transforms = vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::L).all_transforms(),
+        ];
+        let encoding = encode_cnf(&create_board(5, 2), &transforms, &[Supply::Exactly(1); 2]);
+        let encoding = encoding.unwrap();
+        let straight: Vec<String> = encoding
+            .placements
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.piece.name_char() == 'I')
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+        assert_eq!(2, straight.len());
+
+        assert_eq!(
+            Some(Error::WrongPieceCount {
+                name: 'I',
+                count: 2,
+                supply: Supply::Exactly(1)
+            }),
+            encoding
+                .board_from_assignment(&format!("v {} 0", straight.join(" ")))
+                .err()
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Handing a board to an external exact cover solver such as Knuth's DLX1. Each open cell and
+//! each piece that has to be placed is an item, and each placement that fits an option listing
+//! the items it covers, in the plain text format those solvers read.
+
+use crate::board::{check_piece_counts, Board, Placement, Supply};
+use crate::error::Error;
+use crate::pieces::Piece;
+
+/// A board and its pieces as an exact cover problem.
+#[derive(Clone, Debug)]
+pub struct ExactCoverMatrix<'a> {
+    /// The board with any fixed pieces placed, which solutions are built on.
+    pub board: Board<'a>,
+    /// Item names, primary items first. Cells are named like `r2c10` and pieces by their
+    /// letter, followed by their position in the piece set when two share a letter.
+    pub items: Vec<String>,
+    /// How many of `items` are primary and must be covered exactly once. The rest are pieces
+    /// that may be left out, covered at most once.
+    pub primary: usize,
+    pub placements: Vec<Placement<'a>>,
+    /// The indices of the items each placement covers.
+    pub options: Vec<Vec<usize>>,
+    // The piece set, the supply left after fixed pieces and each option's group, for checking
+    // a solution's piece counts.
+    transforms: &'a [Vec<Piece>],
+    supply: Vec<Supply>,
+    groups: Vec<usize>,
+}
+
+fn cell_item(row: usize, column: usize) -> String {
+    format!("r{}c{}", row, column)
+}
+
+/// Builds the exact cover matrix for filling every open cell of a board, honouring its
+/// constraints. Pieces with `Exactly(1)` are primary items, `AtMost(1)` secondary ones and
+/// `Unlimited` pieces have no item at all; larger counts aren't supported.
+pub fn exact_cover_matrix<'a>(
+    board: &Board<'a>,
+    transforms: &'a [Vec<Piece>],
+    supply: &[Supply],
+) -> Result<ExactCoverMatrix<'a>, Error> {
+    if let Some(unsupported) = supply
+        .iter()
+        .find(|s| matches!(s, Supply::Exactly(n) | Supply::AtMost(n) if *n > 1))
+    {
+        return Err(Error::UnsupportedSupply(*unsupported));
+    }
+
+    let (board, remaining) = board.with_fixed_pieces(transforms, supply)?;
+
+    // Items for cells, then required pieces, then optional ones, remembering where each went.
+    let mut items = vec![];
+    let mut cell_items = vec![None; board.filled.len()];
+    for (index, filled) in board.filled.iter().enumerate() {
+        if !filled {
+            cell_items[index] = Some(items.len());
+            items.push(cell_item(index / board.width, index % board.width));
+        }
+    }
+
+    let piece_item = |group: usize| {
+        let name = transforms[group][0].name_char();
+        if transforms
+            .iter()
+            .filter(|t| t[0].name_char() == name)
+            .count()
+            > 1
+        {
+            format!("{}{}", name, group + 1)
+        } else {
+            name.to_string()
+        }
+    };
+    let mut group_items = vec![None; transforms.len()];
+    for required in [true, false] {
+        for (group, group_supply) in remaining.iter().enumerate() {
+            if group_supply.available()
+                && *group_supply != Supply::Unlimited
+                && group_supply.required() == required
+            {
+                group_items[group] = Some(items.len());
+                items.push(piece_item(group));
+            }
+        }
+    }
+    let primary =
+        cell_items.iter().flatten().count() + remaining.iter().filter(|s| s.required()).count();
+
+    let mut placements = vec![];
+    let mut options = vec![];
+    let mut groups = vec![];
+    for (group, placement) in board.legal_placements(transforms, &remaining) {
+        let mut option: Vec<usize> = board
+            .covered_cells(&placement)
+            .iter()
+            .filter_map(|(row, column)| cell_items[row * board.width + column])
+            .collect();
+        option.extend(group_items[group]);
+
+        placements.push(placement);
+        options.push(option);
+        groups.push(group);
+    }
+
+    Ok(ExactCoverMatrix {
+        board,
+        items,
+        primary,
+        placements,
+        options,
+        transforms,
+        supply: remaining,
+        groups,
+    })
+}
+
+impl<'a> ExactCoverMatrix<'a> {
+    /// The matrix in the format read by DLX1 and similar solvers: a line of items, with `|`
+    /// before any secondary ones, then a line per option.
+    pub fn to_dlx(&self) -> String {
+        let mut lines = vec![format!(
+            "| {}x{} board, {} options",
+            self.board.width,
+            self.board.height,
+            self.options.len()
+        )];
+
+        let mut item_line = self.items[..self.primary].join(" ");
+        if self.primary < self.items.len() {
+            item_line += &format!(" | {}", self.items[self.primary..].join(" "));
+        }
+        lines.push(item_line);
+        for option in self.options.iter() {
+            let names: Vec<&str> = option.iter().map(|i| self.items[*i].as_str()).collect();
+            lines.push(names.join(" "));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads solutions written by an exact cover solver as the options they use, one per line,
+    /// into name grids. Words that aren't item names, such as solution numbers, are skipped, and
+    /// each solution ends once the board is full. A solution placing a piece more or less often
+    /// than its supply allows is rejected.
+    pub fn solutions_from_dlx(&self, output: &str) -> Result<Vec<String>, Error> {
+        let mut solutions = vec![];
+        let mut board = self.board.clone();
+        let mut placed = vec![];
+
+        for line in output.lines() {
+            let mut used: Vec<usize> = line
+                .split_whitespace()
+                .filter_map(|word| self.items.iter().position(|item| item == word))
+                .collect();
+            if used.is_empty() {
+                continue;
+            }
+            used.sort_unstable();
+
+            let option = self
+                .options
+                .iter()
+                .position(|option| {
+                    let mut items = option.clone();
+                    items.sort_unstable();
+                    items == used
+                })
+                .ok_or_else(|| Error::UnknownOption(line.trim().to_string()))?;
+            board.try_place(self.placements[option].clone())?;
+            placed.push(self.groups[option]);
+
+            if board.filled.iter().all(|f| *f) {
+                check_piece_counts(self.transforms, &self.supply, placed.drain(..))?;
+                solutions.push(board.name_grid());
+                board = self.board.clone();
+            }
+        }
+
+        // A solution the output stopped partway through.
+        match board.filled.iter().position(|f| !f) {
+            Some(index) if board.placements.len() > self.board.placements.len() => {
+                Err(Error::UncoveredCell {
+                    row: index / board.width,
+                    column: index % board.width,
+                })
+            }
+            _ => Ok(solutions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{create_board, Branching, Supply};
+    use crate::dlx::exact_cover_matrix;
+    use crate::error::Error;
+    use crate::parse::board_from_name_grid;
+    use crate::pieces::{piece_from_name, PentominoName};
+    use crate::testing::{same_placement, u_u_x_i_transforms};
+
+    #[test]
+    fn solutions_read_back_from_their_options() {
+        let transforms = u_u_x_i_transforms();
+        let matrix = exact_cover_matrix(&create_board(5, 4), &transforms, &[Supply::Exactly(1); 4]);
+        let matrix = matrix.unwrap();
+        assert_eq!(24, matrix.items.len());
+        assert_eq!(["U1", "U2", "X", "I"], matrix.items[20..]);
+
+        let solutions = create_board(5, 4)
+            .find_solutions(&transforms, Branching::MostConstrainedCell)
+            .solutions;
+        let mut output = String::new();
+        for (number, solution) in solutions.iter().enumerate() {
+            output += &format!("{}:\n", number + 1);
+            let solved = board_from_name_grid(solution, &transforms).unwrap();
+            for placement in solved.placements.iter() {
+                let option = matrix
+                    .placements
+                    .iter()
+                    .position(|p| same_placement(p, placement))
+                    .unwrap();
+                let names: Vec<&str> = matrix.options[option]
+                    .iter()
+                    .map(|i| matrix.items[*i].as_str())
+                    .collect();
+                output += &format!(" {} (1 of 2)\n", names.join(" "));
+            }
+        }
+
+        assert_eq!(solutions, matrix.solutions_from_dlx(&output).unwrap());
+    }
+
+    #[test]
+    fn writes_dlx_and_rejects_bad_solutions() {
+        let transforms = vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::L).all_transforms(),
+        ];
+        let matrix = exact_cover_matrix(
+            &create_board(5, 1),
+            &transforms,
+            &[Supply::Exactly(1), Supply::AtMost(1)],
+        );
+        let matrix = matrix.unwrap();
+
+        assert_eq!(
+            "| 5x1 board, 1 options\nr0c0 r0c1 r0c2 r0c3 r0c4 I | L\nr0c0 r0c1 r0c2 r0c3 r0c4 I\n",
+            matrix.to_dlx()
+        );
+        assert_eq!(
+            vec!["IIIII".to_string()],
+            matrix
+                .solutions_from_dlx("r0c4 r0c3 r0c2 r0c1 r0c0 I")
+                .unwrap()
+        );
+        assert_eq!(
+            Some(Error::UnknownOption("r0c0 I".to_string())),
+            matrix.solutions_from_dlx("r0c0 I").err()
+        );
+        assert_eq!(
+            Some(Error::UnsupportedSupply(Supply::AtMost(2))),
+            exact_cover_matrix(&create_board(5, 1), &transforms, &[Supply::AtMost(2); 2]).err()
+        );
+    }
+
+    #[test]
+    fn rejects_solutions_that_place_a_piece_twice() {
+        let transforms = vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::L).all_transforms(),
+        ];
+        let matrix = exact_cover_matrix(&create_board(5, 2), &transforms, &[Supply::Exactly(1); 2]);
+        let matrix = matrix.unwrap();
+
+        assert_eq!(
+            Some(Error::WrongPieceCount {
+                name: 'I',
+                count: 2,
+                supply: Supply::Exactly(1)
+            }),
+            matrix
+                .solutions_from_dlx("r0c0 r0c1 r0c2 r0c3 r0c4 I\nr1c0 r1c1 r1c2 r1c3 r1c4 I\n")
+                .err()
+        );
+    }
+}
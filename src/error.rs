@@ -0,0 +1,171 @@
+use std::fmt;
+
+use crate::board::{PlacementProblems, Supply};
+
+/// Why a piece couldn't be built, a board changed or read, or a solution accepted, for input
+/// that comes from outside the program rather than the built-in piece set. Rows and columns
+/// count from 0, as on the board.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    EmptyTemplate,
+    EmptyGrid,
+    /// A row of a piece template or name grid that isn't as wide as the first.
+    RaggedRow {
+        row: usize,
+        width: usize,
+        expected: usize,
+    },
+    /// A template with more rows or columns than a piece can have.
+    TemplateTooLarge {
+        rows: usize,
+        columns: usize,
+    },
+    WrongSquareCount {
+        name: char,
+        count: usize,
+    },
+    UnknownPiece(char),
+    /// Cells of a name grid that no transform of the named piece covers.
+    UnmatchedRegion {
+        name: char,
+        row: usize,
+        column: usize,
+    },
+    /// A solution using a piece more or less often than the piece set has it.
+    WrongCellCount {
+        name: char,
+        expected: usize,
+        actual: usize,
+    },
+    /// A solution from an outside solver placing a piece more often, or less often, than its
+    /// supply allows.
+    WrongPieceCount {
+        name: char,
+        count: usize,
+        supply: Supply,
+    },
+    /// A cell to leave out of a board that isn't on it.
+    HoleOffBoard {
+        row: usize,
+        column: usize,
+    },
+    IllegalPlacement(PlacementProblems),
+    NothingToRemove,
+    /// A supply that can't be written as a SAT formula or exact cover matrix, or tracked by the
+    /// strip counter.
+    UnsupportedSupply(Supply),
+    /// A fixed piece naming a piece or transform that isn't in the piece set.
+    MissingTransform {
+        piece_id: i32,
+        transform: usize,
+    },
+    Unsatisfiable,
+    /// A SAT solver status, such as UNKNOWN after a timeout, that comes with no assignment.
+    NoAssignment(String),
+    BadLiteral(String),
+    UnknownVariable(i64),
+    /// A line of solver output listing items that no option covers.
+    UnknownOption(String),
+    /// A cell left empty by a solution or assignment that should have filled the board.
+    UncoveredCell {
+        row: usize,
+        column: usize,
+    },
+    /// A strip too tall, or with too many pieces, for the strip counter.
+    UnsupportedStrip {
+        height: usize,
+        pieces: usize,
+    },
+    /// A line of a benchmark results file that isn't a result.
+    BadBenchResult(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyTemplate => write!(f, "piece template has no rows"),
+            Error::EmptyGrid => write!(f, "solution grid is empty"),
+            Error::RaggedRow {
+                row,
+                width,
+                expected,
+            } => write!(
+                f,
+                "row {} has width {} but expected {}",
+                row, width, expected
+            ),
+            Error::TemplateTooLarge { rows, columns } => {
+                write!(
+                    f,
+                    "a {}x{} template is too large for a piece",
+                    columns, rows
+                )
+            }
+            Error::WrongSquareCount { name, count } => write!(
+                f,
+                "expected five squares but got {} for piece name {}",
+                count, name
+            ),
+            Error::UnknownPiece(name) => write!(f, "no piece named {} in the piece set", name),
+            Error::UnmatchedRegion { name, row, column } => write!(
+                f,
+                "cells named {} starting at row {} column {} do not match the piece shapes",
+                name, row, column
+            ),
+            Error::WrongCellCount {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "expected {} cells named {} but found {}",
+                expected, name, actual
+            ),
+            Error::WrongPieceCount {
+                name,
+                count,
+                supply,
+            } => write!(
+                f,
+                "piece {} is placed {} times but its supply is {:?}",
+                name, count, supply
+            ),
+            Error::HoleOffBoard { row, column } => {
+                write!(f, "hole at row {} column {} is off the board", row, column)
+            }
+            Error::IllegalPlacement(problems) => write!(f, "{}", problems),
+            Error::NothingToRemove => write!(f, "no pieces on the board to remove"),
+            Error::UnsupportedSupply(supply) => {
+                write!(f, "a supply of {:?} isn't supported by this solver", supply)
+            }
+            Error::MissingTransform {
+                piece_id,
+                transform,
+            } => write!(
+                f,
+                "no transform {} of piece {} to fix in place",
+                transform, piece_id
+            ),
+            Error::Unsatisfiable => write!(f, "the SAT solver found no solution"),
+            Error::NoAssignment(status) => {
+                write!(f, "the SAT solver gave no assignment, only {}", status)
+            }
+            Error::BadLiteral(literal) => write!(f, "{} is not a literal", literal),
+            Error::UnknownVariable(variable) => {
+                write!(f, "variable {} doesn't stand for a placement", variable)
+            }
+            Error::UnknownOption(line) => write!(f, "\"{}\" doesn't match any option", line),
+            Error::UncoveredCell { row, column } => {
+                write!(f, "cell at row {} column {} is left empty", row, column)
+            }
+            Error::UnsupportedStrip { height, pieces } => write!(
+                f,
+                "can't count strips {} cells tall with {} pieces",
+                height, pieces
+            ),
+            Error::BadBenchResult(line) => write!(f, "\"{}\" is not a benchmark result", line),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,60 @@
+//! Stopping long searches early. Every board checks a stop flag as it searches and, once the
+//! flag is set, returns the solutions found so far with `SearchResult::complete` false.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The stop flag boards use unless given their own, set by [`stop_on_ctrl_c`].
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks every search using the shared flag to stop.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Lets searches using the shared flag run again after an interrupt, for callers that go on
+/// searching. Ctrl-C only stops searches once, so call [`stop_on_ctrl_c`] again to catch the
+/// next one too.
+pub fn clear_interrupt() {
+    INTERRUPTED.store(false, Ordering::Relaxed);
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn stopped(flag: &AtomicBool) -> bool {
+    flag.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+mod signal {
+    const SIGINT: i32 = 2;
+    const SIG_DFL: usize = 0;
+
+    extern "C" {
+        fn signal(signum: i32, handler: usize) -> usize;
+    }
+
+    // Only stores to an atomic and resets the handler, both safe inside a signal handler. A
+    // second Ctrl-C then ends the process as usual.
+    extern "C" fn handle_interrupt(_: i32) {
+        super::interrupt();
+        unsafe {
+            signal(SIGINT, SIG_DFL);
+        }
+    }
+
+    pub(super) fn install() {
+        let handler: extern "C" fn(i32) = handle_interrupt;
+        unsafe {
+            signal(SIGINT, handler as usize);
+        }
+    }
+}
+
+/// Makes Ctrl-C stop the searches using the shared flag instead of ending the process, so what
+/// they found so far can still be reported. Does nothing on platforms without Unix signals.
+pub fn stop_on_ctrl_c() {
+    #[cfg(unix)]
+    signal::install();
+}
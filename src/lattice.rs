@@ -0,0 +1,520 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::board::{SearchResult, Supply};
+use crate::cover::{fill_cells, CoverPlacement, FillControls};
+use crate::error::Error;
+use crate::parse::parse_name_grid;
+use crate::report::Reporter;
+
+/// The shape of the cells a board and its pieces are made of, besides squares.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lattice {
+    /// Hexagons, in rows that each start half a cell further right, so a cell touches the two
+    /// below it at the same column and one to the left.
+    Hexagonal,
+    /// Triangles, alternately pointing up and down along each row, with the top left one
+    /// pointing up.
+    Triangular,
+}
+
+/// A cell in three coordinates: cube coordinates summing to 0 for hexagons, and coordinates
+/// summing to 1 for triangles pointing up or 2 for those pointing down.
+pub type LatticeCell = (i32, i32, i32);
+
+impl Lattice {
+    /// The cell at a row and column of a board grid.
+    pub fn cell_at(self, row: usize, column: usize) -> LatticeCell {
+        let (row, column) = (row as i32, column as i32);
+
+        match self {
+            Lattice::Hexagonal => (column, -column - row, row),
+            Lattice::Triangular if (row + column) % 2 == 0 => {
+                let along = (column - row) / 2;
+                (along, -row - along, 1 + row)
+            }
+            Lattice::Triangular => {
+                let along = (column - 1 - row) / 2;
+                (along + 1, -row - along, 1 + row)
+            }
+        }
+    }
+
+    fn neighbours(self, (a, b, c): LatticeCell) -> Vec<LatticeCell> {
+        match self {
+            Lattice::Hexagonal => [
+                (1, -1, 0),
+                (1, 0, -1),
+                (0, 1, -1),
+                (-1, 1, 0),
+                (-1, 0, 1),
+                (0, -1, 1),
+            ]
+            .iter()
+            .map(|(da, db, dc)| (a + da, b + db, c + dc))
+            .collect(),
+            Lattice::Triangular => {
+                let step = if a + b + c == 1 { 1 } else { -1 };
+                vec![(a + step, b, c), (a, b + step, c), (a, b, c + step)]
+            }
+        }
+    }
+
+    // A sixth of a turn about a corner shared by the cells around the origin.
+    fn turn((a, b, c): LatticeCell, lattice: Lattice) -> LatticeCell {
+        match lattice {
+            Lattice::Hexagonal => (-c, -a, -b),
+            Lattice::Triangular => (1 - b, 1 - c, 1 - a),
+        }
+    }
+}
+
+fn reflect((a, b, c): LatticeCell) -> LatticeCell {
+    (a, c, b)
+}
+
+// Moves cells so their smallest first and last coordinates are 0, which keeps the sum of each
+// cell and so the direction triangles point.
+fn normalise(cells: &[LatticeCell]) -> Vec<LatticeCell> {
+    let shift_a = -cells.iter().map(|c| c.0).min().unwrap_or(0);
+    let shift_c = -cells.iter().map(|c| c.2).min().unwrap_or(0);
+    let mut result: Vec<LatticeCell> = cells
+        .iter()
+        .map(|(a, b, c)| (a + shift_a, b - shift_a - shift_c, c + shift_c))
+        .collect();
+    result.sort_unstable();
+
+    result
+}
+
+fn rotated_cell_sets(cells: &[LatticeCell], lattice: Lattice) -> Vec<Vec<LatticeCell>> {
+    let mut result = vec![];
+    let mut current = normalise(cells);
+
+    for _ in 0..6 {
+        result.push(current.clone());
+        let turned: Vec<LatticeCell> = current
+            .iter()
+            .map(|cell| Lattice::turn(*cell, lattice))
+            .collect();
+        current = normalise(&turned);
+    }
+
+    result
+}
+
+fn all_cell_sets(cells: &[LatticeCell], lattice: Lattice) -> BTreeSet<Vec<LatticeCell>> {
+    let reflected: Vec<LatticeCell> = cells.iter().map(|c| reflect(*c)).collect();
+
+    rotated_cell_sets(cells, lattice)
+        .into_iter()
+        .chain(rotated_cell_sets(&reflected, lattice))
+        .collect()
+}
+
+/// A piece made of hexagons or triangles joined along their edges.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyform {
+    pub id: i32,
+    pub name: char,
+    pub lattice: Lattice,
+    /// The cells, moved as close to the origin as possible and sorted.
+    pub cells: Vec<LatticeCell>,
+}
+
+impl Polyform {
+    /// The distinct rotations of this piece, at most 6.
+    pub fn rotations(&self) -> Vec<Polyform> {
+        let cell_sets: BTreeSet<Vec<LatticeCell>> = rotated_cell_sets(&self.cells, self.lattice)
+            .into_iter()
+            .collect();
+
+        cell_sets
+            .into_iter()
+            .map(|cells| Polyform {
+                cells,
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// The distinct rotations and reflections of this piece, at most 12.
+    pub fn all_orientations(&self) -> Vec<Polyform> {
+        all_cell_sets(&self.cells, self.lattice)
+            .into_iter()
+            .map(|cells| Polyform {
+                cells,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+/// Builds a piece from rows of `*` (solid) and `.` (empty) cells laid out as on a board.
+pub fn polyform_from_template(
+    id: i32,
+    name: char,
+    lattice: Lattice,
+    template: Vec<&str>,
+) -> Polyform {
+    let cells: Vec<LatticeCell> = template
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '*')
+                .map(move |(column, _)| lattice.cell_at(row, column))
+        })
+        .collect();
+
+    Polyform {
+        id,
+        name,
+        lattice,
+        cells: normalise(&cells),
+    }
+}
+
+// Every piece of `size` cells counting reflections as the same, grown one cell at a time and
+// named with letters from A in a fixed order.
+fn enumerate(lattice: Lattice, size: usize) -> Vec<Polyform> {
+    let start = match lattice {
+        Lattice::Hexagonal => (0, 0, 0),
+        Lattice::Triangular => (0, 0, 1),
+    };
+    let mut shapes: BTreeSet<Vec<LatticeCell>> = [vec![start]].into_iter().collect();
+
+    for _ in 1..size {
+        let mut grown = BTreeSet::new();
+        for shape in shapes.iter() {
+            for cell in shape {
+                for neighbour in lattice.neighbours(*cell) {
+                    if !shape.contains(&neighbour) {
+                        let mut cells = shape.clone();
+                        cells.push(neighbour);
+                        grown.insert(all_cell_sets(&cells, lattice).into_iter().next().unwrap());
+                    }
+                }
+            }
+        }
+        shapes = grown;
+    }
+
+    shapes
+        .into_iter()
+        .zip('A'..='Z')
+        .enumerate()
+        .map(|(i, (cells, name))| Polyform {
+            id: i as i32 + 1,
+            name,
+            lattice,
+            cells,
+        })
+        .collect()
+}
+
+/// The 12 hexiamonds, made of six triangles, named A to L. There are no agreed single letter
+/// names for them, so the letters follow the order they are enumerated in.
+pub fn hexiamonds() -> Vec<Polyform> {
+    enumerate(Lattice::Triangular, 6)
+}
+
+/// The 22 pentahexes, made of five hexagons, named A to V in the order they are enumerated.
+pub fn pentahexes() -> Vec<Polyform> {
+    enumerate(Lattice::Hexagonal, 5)
+}
+
+/// A board of hexagons or triangles laid out in rows, holding the name of the piece in each
+/// cell, '.' when empty or '#' for positions that aren't part of the board.
+#[derive(Clone, Debug)]
+pub struct LatticeBoard {
+    lattice: Lattice,
+    width: usize,
+    height: usize,
+    cells: Vec<char>,
+    controls: FillControls,
+}
+
+/// A board with every position in the rows and columns open.
+pub fn create_lattice_board(lattice: Lattice, width: usize, height: usize) -> LatticeBoard {
+    LatticeBoard {
+        lattice,
+        width,
+        height,
+        cells: vec!['.'; width * height],
+        controls: FillControls::default(),
+    }
+}
+
+/// Reads a board from rows of '.' for open cells and '#' for positions left out, so boards of
+/// any outline fit in the rows and columns. Letters are kept as pieces already placed.
+pub fn lattice_board_from_mask(lattice: Lattice, mask: &str) -> Result<LatticeBoard, Error> {
+    let grid = parse_name_grid(mask)?;
+
+    Ok(LatticeBoard {
+        lattice,
+        width: grid.width,
+        height: grid.height,
+        cells: grid.cells,
+        controls: FillControls::default(),
+    })
+}
+
+impl LatticeBoard {
+    pub fn lattice(&self) -> Lattice {
+        self.lattice
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The name in each position, row by row.
+    pub fn cells(&self) -> &[char] {
+        &self.cells
+    }
+
+    /// Checks `stop` rather than the Ctrl-C flag while searching.
+    pub fn set_stop_flag(&mut self, stop: &'static AtomicBool) {
+        self.controls.stop = stop;
+    }
+
+    /// How many searches, one per placement covering the first open position, may run on
+    /// threads at once. 0 runs all of them together.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.controls.threads = threads;
+    }
+
+    /// Reports every tiling as soon as the search finds it.
+    pub fn set_reporter(&mut self, reporter: Reporter) {
+        self.controls.reporter = Some(reporter);
+    }
+
+    fn cell_indices(&self) -> HashMap<LatticeCell, usize> {
+        (0..self.cells.len())
+            .filter(|i| self.cells[*i] != '#')
+            .map(|i| (self.lattice.cell_at(i / self.width, i % self.width), i))
+            .collect()
+    }
+
+    // The positions a piece covers when its first cell is moved onto the cell at `index`, if
+    // that doesn't take it off the board. Triangles can only move onto cells pointing the same
+    // way.
+    fn cells_at(
+        &self,
+        polyform: &Polyform,
+        index: usize,
+        indices: &HashMap<LatticeCell, usize>,
+    ) -> Option<Vec<usize>> {
+        let target = self.lattice.cell_at(index / self.width, index % self.width);
+        let first = polyform.cells[0];
+        let shift = (target.0 - first.0, target.1 - first.1, target.2 - first.2);
+        if shift.0 + shift.1 + shift.2 != 0 {
+            return None;
+        }
+
+        polyform
+            .cells
+            .iter()
+            .map(|(a, b, c)| {
+                indices
+                    .get(&(a + shift.0, b + shift.1, c + shift.2))
+                    .copied()
+            })
+            .collect()
+    }
+
+    /// Places a piece with its first cell on the given row and column, if it stays on the board
+    /// and doesn't overlap anything.
+    pub fn try_add(&mut self, polyform: &Polyform, row: usize, column: usize) -> bool {
+        let indices = self.cell_indices();
+
+        match self.cells_at(polyform, row * self.width + column, &indices) {
+            Some(cells) if cells.iter().all(|i| self.cells[*i] == '.') => {
+                for i in cells {
+                    self.cells[i] = polyform.name;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The rows of piece names separated by spaces, as used for solutions.
+    pub fn name_grid(&self) -> String {
+        name_grid(self.width, &self.cells)
+    }
+
+    /// The board as it looks, one row per line. Rows of hexagons are indented a further half
+    /// cell each, and '#' positions are left blank.
+    pub fn render(&self) -> String {
+        let rows: Vec<String> = self
+            .cells
+            .chunks(self.width)
+            .enumerate()
+            .map(|(row, cells)| {
+                let cells = cells.iter().map(|c| if *c == '#' { ' ' } else { *c });
+                match self.lattice {
+                    Lattice::Hexagonal => {
+                        let spaced: Vec<String> = cells.map(String::from).collect();
+                        " ".repeat(row) + &spaced.join(" ")
+                    }
+                    Lattice::Triangular => cells.collect(),
+                }
+            })
+            .collect();
+
+        rows.join("\n")
+    }
+
+    fn all_placements(&self, orientations: &[Vec<Polyform>]) -> Vec<CoverPlacement> {
+        let indices = self.cell_indices();
+        let mut placements = vec![];
+
+        for (group, group_orientations) in orientations.iter().enumerate() {
+            for orientation in group_orientations {
+                for index in indices.values() {
+                    if let Some(cells) = self.cells_at(orientation, *index, &indices) {
+                        placements.push(CoverPlacement {
+                            group,
+                            name: orientation.name,
+                            cells,
+                        });
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+
+    pub fn find_solutions(&mut self, orientations: &Arc<Vec<Vec<Polyform>>>) -> SearchResult {
+        self.find_solutions_with_supply(orientations, &vec![Supply::Exactly(1); orientations.len()])
+    }
+
+    /// Searches for ways to fill every open cell using each piece as many times as its supply
+    /// allows, working along the rows.
+    pub fn find_solutions_with_supply(
+        &mut self,
+        orientations: &Arc<Vec<Vec<Polyform>>>,
+        supply: &[Supply],
+    ) -> SearchResult {
+        let width = self.width;
+
+        fill_cells(
+            &self.cells,
+            (0..self.cells.len()).collect(),
+            self.all_placements(orientations),
+            supply,
+            move |cells| name_grid(width, cells),
+            self.controls.clone(),
+        )
+    }
+}
+
+fn name_grid(width: usize, cells: &[char]) -> String {
+    let rows: Vec<String> = cells
+        .chunks(width)
+        .map(|row| row.iter().collect())
+        .collect();
+
+    rows.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Supply;
+    use crate::lattice::{
+        create_lattice_board, hexiamonds, lattice_board_from_mask, pentahexes,
+        polyform_from_template, Lattice,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn grid_neighbours_share_an_edge() {
+        let triangles = Lattice::Triangular;
+        let up = triangles.cell_at(2, 4);
+
+        for neighbour in [(2, 3), (2, 5), (3, 4)] {
+            assert!(triangles
+                .neighbours(up)
+                .contains(&triangles.cell_at(neighbour.0, neighbour.1)));
+        }
+        assert!(triangles
+            .neighbours(triangles.cell_at(2, 5))
+            .contains(&triangles.cell_at(1, 5)));
+
+        let hexagons = Lattice::Hexagonal;
+        for neighbour in [(0, 2), (0, 3), (1, 1), (1, 3), (2, 1), (2, 2)] {
+            assert!(hexagons
+                .neighbours(hexagons.cell_at(1, 2))
+                .contains(&hexagons.cell_at(neighbour.0, neighbour.1)));
+        }
+    }
+
+    #[test]
+    fn turns_pieces_a_sixth_at_a_time() {
+        let bar = polyform_from_template(1, 'I', Lattice::Hexagonal, vec!["***"]);
+        let hexagon = polyform_from_template(2, 'O', Lattice::Triangular, vec!["***", "***"]);
+        let diamond = polyform_from_template(3, 'D', Lattice::Triangular, vec!["**"]);
+
+        assert_eq!(3, bar.all_orientations().len());
+        assert_eq!(1, hexagon.all_orientations().len());
+        assert_eq!(3, diamond.rotations().len());
+    }
+
+    #[test]
+    fn enumerates_hexiamonds_and_pentahexes() {
+        assert_eq!(12, hexiamonds().len());
+        assert_eq!(22, pentahexes().len());
+        assert_eq!('V', pentahexes()[21].name);
+    }
+
+    #[test]
+    fn counts_tilings_of_hexagons_with_diamonds() {
+        let diamond = Arc::new(vec![polyform_from_template(
+            1,
+            'D',
+            Lattice::Triangular,
+            vec!["**"],
+        )
+        .all_orientations()]);
+        let count = |mask| {
+            lattice_board_from_mask(Lattice::Triangular, mask)
+                .unwrap()
+                .find_solutions_with_supply(&diamond, &[Supply::Unlimited])
+                .solutions
+                .len()
+        };
+
+        assert_eq!(2, count("... ..."));
+        assert_eq!(20, count("##.....## #.......# #.......# ##.....##"));
+    }
+
+    #[test]
+    fn places_and_renders_hexagons() {
+        let dihex = polyform_from_template(1, 'D', Lattice::Hexagonal, vec!["**"]);
+        let mut board = create_lattice_board(Lattice::Hexagonal, 2, 2);
+
+        assert!(board.try_add(&dihex, 0, 0));
+        assert!(!board.try_add(&dihex, 1, 1));
+        assert_eq!("DD ..", board.name_grid());
+        assert_eq!("D D\n . .", board.render());
+
+        let pieces = Arc::new(vec![dihex.all_orientations()]);
+        let mut empty = create_lattice_board(Lattice::Hexagonal, 2, 2);
+        assert_eq!(
+            2,
+            empty
+                .find_solutions_with_supply(&pieces, &[Supply::Unlimited])
+                .solutions
+                .len()
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! Finding Katamino (pentomino) solutions.
+//!
+//! Pieces are built with [`pieces::piece_from_name`], expanded into their rotations and
+//! reflections with [`pieces::Piece::all_transforms`] and placed on a [`board::Board`] by
+//! [`board::Board::find_solutions`]. Solutions come back as name grids, which can be
+//! canonicalised, verified or parsed back into placements.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use katamino::board::{create_board, Branching};
+//! use katamino::pieces::{piece_from_name, PentominoName, Piece};
+//!
+//! let pieces = [
+//!     piece_from_name(1, PentominoName::U),
+//!     piece_from_name(2, PentominoName::U),
+//!     piece_from_name(3, PentominoName::X),
+//!     piece_from_name(4, PentominoName::I),
+//! ];
+//! let transforms = Arc::new(pieces.iter().map(Piece::all_transforms).collect());
+//! let mut board = create_board(5, 4);
+//!
+//! let result = board.find_solutions(&transforms, Branching::PieceOrder);
+//! assert_eq!(1, result.solutions.len());
+//! ```
+
+pub mod bench;
+pub mod bigint;
+pub mod board;
+pub mod canonical;
+pub mod constraints;
+mod cover;
+pub mod cubes;
+pub mod dimacs;
+pub mod dlx;
+pub mod error;
+pub mod interrupt;
+pub mod lattice;
+pub mod parse;
+pub mod pieces;
+pub mod report;
+pub mod stats;
+#[cfg(test)]
+mod testing;
+pub mod transfer;
+pub mod verify;
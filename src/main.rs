@@ -1,96 +1,412 @@
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::process;
 use std::sync::Arc;
 use std::time::Instant;
 
-use crate::board::create_board;
-use crate::pieces::{piece_from_name, PentominoName, Piece};
+use katamino::bench::{
+    bench_problems, parse_bench_results, regressions, run_bench, thread_settings, Backend,
+    BenchProblem, BenchResult, Regression, BENCH_HEADER,
+};
+use katamino::board::{
+    create_board, create_board_with_holes, create_cylinder, create_torus, Board, Branching, Supply,
+    Topology, Uniqueness,
+};
+use katamino::canonical::{
+    canonicalise_layered_solution_string, canonicalise_one_sided_solution_string,
+    canonicalise_solution_string, canonicalise_square_solution_string,
+    canonicalise_wrapped_solution_string,
+};
+use katamino::cubes::{create_cuboid, pentacubes, polycube_from_piece, Polycube};
+use katamino::dimacs::encode_cnf;
+use katamino::dlx::exact_cover_matrix;
+use katamino::interrupt::{interrupted, stop_on_ctrl_c};
+use katamino::lattice::{hexiamonds, lattice_board_from_mask, pentahexes, Lattice, Polyform};
+use katamino::parse::{board_from_clue_grid, piece_names};
+use katamino::pieces::{one_sided_pieces, standard_pieces, Piece};
+use katamino::report::{Reporter, SearchEvent};
+use katamino::transfer::count_strip_tilings;
+use katamino::verify::verify_solution;
 
-mod board;
-mod pieces;
+// Prints each solution as it is found.
+fn print_solutions() -> Reporter {
+    Reporter::new(|event| {
+        if let SearchEvent::Solution(solution) = event {
+            println!("Found solution: {}", solution);
+        }
+    })
+}
+
+// Prints each solution as it is found, drawn in colour, and how much of the search is done.
+fn print_search(transforms: Arc<Vec<Vec<Piece>>>) -> Reporter {
+    Reporter::new(move |event| match event {
+        SearchEvent::Solution(solution) => {
+            println!("Found solution: {}", solution);
+            print!("{}", render_name_grid(solution, &transforms));
+        }
+        SearchEvent::Progress { done, total } => println!("{}%", done * 100 / total.max(&1)),
+    })
+}
 
-fn canonicalise_solution_string(solution: &str) -> String {
-    fn flip_horizontally(solution: &[String]) -> Vec<String> {
-        solution.iter().map(|s| s.chars().rev().collect()).collect()
+// Draws a name grid in colour the way `Board::render` does, each piece in the colour of the
+// first piece with its letter.
+fn render_name_grid(name_grid: &str, transforms: &[Vec<Piece>]) -> String {
+    let mut rendered = String::new();
+
+    for row in name_grid.split(' ') {
+        for name in row.chars() {
+            match transforms.iter().find(|t| t[0].name_char() == name) {
+                Some(group) => rendered += &format!("\u{001b}[48;5;{}m ", group[0].id()),
+                None => rendered += "\u{001b}[0m ",
+            }
+        }
+        rendered += "\u{001b}[0m\n";
     }
 
-    fn rotations(solution: &[String]) -> Vec<Vec<String>> {
-        // currently only does a 180 flip as focusing on rectangle cases
-        vec![solution
-            .iter()
-            .map(|s| s.chars().rev().collect())
-            .rev()
-            .collect()]
+    rendered
+}
+
+fn verify(solutions: &[String]) {
+    let pieces = standard_pieces();
+    let mut all_valid = true;
+
+    for solution in solutions {
+        match verify_solution(solution, &pieces) {
+            Ok(()) => println!("valid: {}", solution),
+            Err(error) => {
+                println!("invalid: {} ({})", solution, error);
+                all_valid = false;
+            }
+        }
+    }
+
+    if !all_valid {
+        process::exit(1);
+    }
+}
+
+fn solve_clues(clue_grid: &str) {
+    let pieces = standard_pieces();
+    let transforms: Arc<Vec<Vec<Piece>>> =
+        Arc::new(pieces.iter().map(Piece::all_transforms).collect());
+    let mut board = match board_from_clue_grid(clue_grid) {
+        Ok(board) => board,
+        Err(error) => {
+            println!("invalid clues: {}", error);
+            process::exit(1);
+        }
+    };
+
+    let supply = vec![Supply::AtMost(1); pieces.len()];
+    match board.check_uniqueness(&transforms, &supply, Branching::MostConstrainedCell) {
+        Uniqueness::NoSolution => println!("no solution satisfies the clues"),
+        Uniqueness::Unique(solution) => println!("unique solution: {}", solution),
+        Uniqueness::Interrupted => println!("stopped before the clues were checked"),
+        Uniqueness::Ambiguous(solutions) => {
+            println!("{} solutions satisfy the clues:", solutions.len());
+            for solution in solutions {
+                println!("{}", solution);
+            }
+        }
     }
+}
 
-    fn less_than(lhs: &[String], rhs: &[String]) -> bool {
-        let l = lhs.iter().next().unwrap();
-        let r = rhs.iter().next().unwrap();
+// Tiles a board of hexagons with the pentahexes, or of triangles with the hexiamonds, using
+// every piece when they exactly fill it and any subset of them otherwise.
+fn solve_lattice(lattice: Lattice, mask: &str) {
+    let mut board = match lattice_board_from_mask(lattice, mask) {
+        Ok(board) => board,
+        Err(error) => {
+            println!("couldn't read board: {}", error);
+            process::exit(1);
+        }
+    };
+    let pieces = match lattice {
+        Lattice::Hexagonal => pentahexes(),
+        Lattice::Triangular => hexiamonds(),
+    };
+    let orientations: Arc<Vec<Vec<Polyform>>> =
+        Arc::new(pieces.iter().map(Polyform::all_orientations).collect());
+    let open = board.cells().iter().filter(|c| **c == '.').count();
+    let supply = if open == pieces[0].cells.len() * pieces.len() {
+        vec![Supply::Exactly(1); pieces.len()]
+    } else {
+        vec![Supply::AtMost(1); pieces.len()]
+    };
 
-        l < r
+    board.set_reporter(print_solutions());
+    let start = Instant::now();
+    let result = board.find_solutions_with_supply(&orientations, &supply);
+    let elapsed = start.elapsed();
+    println!("{}", result.statistics);
+
+    if let Some(first) = result.solutions.first() {
+        let solved = lattice_board_from_mask(lattice, first).unwrap();
+        println!("{}", solved.render());
+    }
+    println!(
+        "found {} solutions in {}ms!",
+        result.solutions.len(),
+        elapsed.as_millis()
+    );
+}
+
+fn count_strips(size: &str, options: &[String]) {
+    let Some((height, length)) = size
+        .split_once('x')
+        .and_then(|(h, l)| Some((h.parse().ok()?, l.parse().ok()?)))
+    else {
+        println!("expected a strip size like 3x20 but got {}", size);
+        process::exit(1);
+    };
+    let names = options.iter().find_map(|o| o.strip_prefix("--pieces="));
+    let transforms: Vec<Vec<Piece>> = standard_pieces()
+        .iter()
+        .filter(|piece| names.is_none_or(|names| names.contains(piece.name_char())))
+        .map(Piece::all_transforms)
+        .collect();
+    let supply = if options.iter().any(|o| o == "--unlimited") {
+        vec![Supply::Unlimited; transforms.len()]
+    } else {
+        vec![Supply::AtMost(1); transforms.len()]
+    };
+
+    let start = Instant::now();
+    match count_strip_tilings(height, length, &transforms, &supply) {
+        Ok(counts) => {
+            for (length, count) in counts.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+                println!("{}x{}: {}", height, length, count);
+            }
+            println!("counted in {}ms!", start.elapsed().as_millis());
+        }
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
     }
+}
+
+// Fills a WxHxD box with the flat pentominoes, or with a subset of the pentacubes.
+fn solve_box(size: &str, use_pentacubes: bool) {
+    let dimensions: Vec<u8> = size.split('x').filter_map(|d| d.parse().ok()).collect();
+    let [width, height, depth] = dimensions[..] else {
+        println!("expected a box size like 5x4x3 but got {}", size);
+        process::exit(1);
+    };
+
+    let pieces: Vec<Polycube> = if use_pentacubes {
+        pentacubes()
+    } else {
+        standard_pieces().iter().map(polycube_from_piece).collect()
+    };
+    let orientations: Arc<Vec<Vec<Polycube>>> =
+        Arc::new(pieces.iter().map(Polycube::all_orientations).collect());
+    let volume = usize::from(width) * usize::from(height) * usize::from(depth);
+    let supply = if volume == 5 * pieces.len() {
+        vec![Supply::Exactly(1); pieces.len()]
+    } else {
+        vec![Supply::AtMost(1); pieces.len()]
+    };
+
+    let mut cuboid = create_cuboid(width, height, depth);
+    cuboid.set_reporter(print_solutions());
+    let start = Instant::now();
+    let result = cuboid.find_solutions_with_supply(&orientations, &supply);
+    let elapsed = start.elapsed();
+    println!("{}", result.statistics);
+
+    let mut unique: Vec<String> = result
+        .solutions
+        .iter()
+        .map(|s| canonicalise_layered_solution_string(s))
+        .collect();
+    unique.sort();
+    unique.dedup();
+    println!(
+        "found {} solutions with {} unique ones in {}ms!",
+        result.solutions.len(),
+        unique.len(),
+        elapsed.as_millis()
+    );
+}
+
+struct SolveSettings {
+    width: usize,
+    height: usize,
+    branching: Branching,
+    reflections: bool,
+    one_sided_set: bool,
+    // Piece letters with how many of each to use, where any letter not listed is left out. When
+    // empty every piece is used once.
+    supply: Vec<(char, Supply)>,
+    // Treat the pieces as a pool to pick from, each used at most once.
+    subset: bool,
+    topology: Topology,
+    // Count solutions with a transposition table of this many megabytes instead of listing them.
+    count_with_table_mb: Option<usize>,
+}
+
+fn parse_supply(supply: &str) -> Vec<(char, Supply)> {
+    supply
+        .split(',')
+        .filter_map(|entry| {
+            let (name, count) = entry.split_once(':')?;
+            let count = if count == "*" {
+                Supply::Unlimited
+            } else {
+                Supply::Exactly(count.parse().ok()?)
+            };
+
+            Some((name.chars().next()?, count))
+        })
+        .collect()
+}
+
+impl SolveSettings {
+    fn from_args(args: &[String]) -> SolveSettings {
+        let one_sided_set = args.iter().any(|a| a == "--one-sided");
+        let (width, height) = args
+            .iter()
+            .find_map(|a| a.strip_prefix("--size="))
+            .and_then(|size| size.split_once('x'))
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .unwrap_or(if one_sided_set { (15, 6) } else { (12, 5) });
 
-    let input_solution: Vec<String> = solution.split_whitespace().map(|s| s.to_string()).collect();
-    let input_rotations = rotations(&input_solution);
-    let flipped_solution = flip_horizontally(&input_solution);
-    let flipped_rotations = rotations(&flipped_solution);
+        SolveSettings {
+            width,
+            height,
+            branching: if args.iter().any(|a| a == "--most-constrained-cell") {
+                Branching::MostConstrainedCell
+            } else {
+                Branching::PieceOrder
+            },
+            reflections: !args.iter().any(|a| a == "--no-reflections"),
+            one_sided_set,
+            supply: args
+                .iter()
+                .find_map(|a| a.strip_prefix("--supply="))
+                .map(parse_supply)
+                .unwrap_or_default(),
+            subset: args.iter().any(|a| a == "--subset"),
+            topology: if args.iter().any(|a| a == "--torus") {
+                Topology::Torus
+            } else if args.iter().any(|a| a == "--cylinder") {
+                Topology::Cylinder
+            } else {
+                Topology::Rectangle
+            },
+            count_with_table_mb: args.iter().any(|a| a == "--count").then(|| {
+                args.iter()
+                    .find_map(|a| a.strip_prefix("--table-mb="))
+                    .and_then(|mb| mb.parse().ok())
+                    .unwrap_or(256)
+            }),
+        }
+    }
 
-    let mut minimum = &input_solution;
-    for solution in input_rotations.iter() {
-        if less_than(solution, minimum) {
-            minimum = solution;
+    fn pieces(&self) -> Vec<Piece> {
+        if self.one_sided_set {
+            one_sided_pieces()
+        } else {
+            standard_pieces()
         }
     }
-    if less_than(&flipped_solution, minimum) {
-        minimum = &flipped_solution
+
+    fn one_sided(&self) -> bool {
+        self.one_sided_set || !self.reflections
+    }
+
+    fn transforms(&self, pieces: &[Piece]) -> Vec<Vec<Piece>> {
+        pieces
+            .iter()
+            .map(|piece| {
+                if self.one_sided() {
+                    piece.to_one_sided().all_transforms()
+                } else {
+                    piece.all_transforms()
+                }
+            })
+            .collect()
     }
-    for solution in flipped_rotations.iter() {
-        if less_than(solution, minimum) {
-            minimum = solution
+
+    fn board<'a>(&self) -> Board<'a> {
+        match self.topology {
+            Topology::Rectangle => create_board(self.width, self.height),
+            Topology::Cylinder => create_cylinder(self.width, self.height),
+            Topology::Torus => create_torus(self.width, self.height),
         }
     }
 
-    minimum.join(" ")
+    fn supply(&self, pieces: &[Piece]) -> Vec<Supply> {
+        if self.subset {
+            vec![Supply::AtMost(1); pieces.len()]
+        } else if self.supply.is_empty() {
+            vec![Supply::Exactly(1); pieces.len()]
+        } else {
+            pieces
+                .iter()
+                .map(|piece| {
+                    self.supply
+                        .iter()
+                        .find(|(name, _)| *name == piece.name_char())
+                        .map_or(Supply::Exactly(0), |(_, supply)| *supply)
+                })
+                .collect()
+        }
+    }
 }
 
-fn main() {
-    let pieces = vec![
-        piece_from_name(94, PentominoName::I),  //  1
-        piece_from_name(208, PentominoName::L), //  2
-        piece_from_name(130, PentominoName::Y), //  3
-        piece_from_name(127, PentominoName::N), //  4
-        piece_from_name(4, PentominoName::V),   //  5
-        piece_from_name(217, PentominoName::P), //  6
-        piece_from_name(11, PentominoName::U),  //  7
-        piece_from_name(6, PentominoName::Z),   //  8
-        piece_from_name(252, PentominoName::F), //  9
-        piece_from_name(28, PentominoName::T),  // 10
-        piece_from_name(10, PentominoName::W),  // 11
-        piece_from_name(1, PentominoName::X),   // 12
-    ];
+fn solve(settings: &SolveSettings) -> usize {
+    let pieces = settings.pieces();
 
     #[cfg(feature = "trace")]
     {
         for piece in pieces.iter() {
-            println!("piece {}:", piece.id);
+            println!("piece {}:", piece.id());
             println!("{}", piece.shape_string());
         }
     }
 
-    let transforms: Arc<Vec<Vec<Piece>>> =
-        Arc::new(pieces.iter().map(Piece::all_transforms).collect());
-    let mut board = create_board(12, 5);
+    let one_sided = settings.one_sided();
+    let transforms: Arc<Vec<Vec<Piece>>> = Arc::new(settings.transforms(&pieces));
+    let canonicalise = |solution: &str| match settings.topology {
+        Topology::Rectangle if one_sided => canonicalise_one_sided_solution_string(solution),
+        Topology::Rectangle => canonicalise_solution_string(solution),
+        topology => canonicalise_wrapped_solution_string(solution, topology, one_sided),
+    };
+    let mut board = settings.board();
+    board.set_reporter(print_search(Arc::clone(&transforms)));
 
     let start = Instant::now();
-    let solutions = board.find_solutions(&transforms);
+    let supply = settings.supply(&pieces);
+    if let Some(table_mb) = settings.count_with_table_mb {
+        let count = board.count_solutions(&transforms, &supply, table_mb << 20);
+        println!("{}", count.statistics);
+        println!(
+            "transposition table: {} entries, {} hits",
+            count.table_entries, count.table_hits
+        );
+        println!(
+            "counted {} solutions, including rotations and reflections, in {}ms!",
+            count.count,
+            start.elapsed().as_millis()
+        );
+        return count.count as usize;
+    }
+    let result = board.find_solutions_with_supply(&transforms, &supply, settings.branching);
     let elapsed = start.elapsed();
+    let solutions = &result.solutions;
+    println!("{}", result.statistics);
 
     if solutions.is_empty() {
         println!("no solution found :( in {} ms", elapsed.as_millis());
+        0
     } else {
         let mut found = HashMap::new();
         for solution in solutions.iter() {
-            let canonical_solution = canonicalise_solution_string(solution);
+            let canonical_solution = canonicalise(solution);
 
             match found.entry(canonical_solution) {
                 Vacant(v) => {
@@ -106,25 +422,371 @@ fn main() {
             }
         }
 
+        if settings.subset {
+            let mut subsets: BTreeMap<String, usize> = BTreeMap::new();
+            for solution in found.values() {
+                *subsets.entry(piece_names(solution)).or_default() += 1;
+            }
+            for (subset, count) in subsets {
+                println!("subset {}: {} unique solutions", subset, count);
+            }
+        }
+
         println!(
             "found {} solutions with {} unique ones in {}ms!",
             solutions.len(),
             found.len(),
             elapsed.as_millis()
         );
+        found.len()
+    }
+}
+
+// Prints the board and pieces as a SAT problem or, given a SAT solver's answer, the solution
+// it describes.
+fn sat(settings: &SolveSettings, answer_file: Option<&str>) {
+    let pieces = settings.pieces();
+    let transforms = settings.transforms(&pieces);
+    let encoding = match encode_cnf(&settings.board(), &transforms, &settings.supply(&pieces)) {
+        Ok(encoding) => encoding,
+        Err(error) => {
+            println!("can't encode the board: {}", error);
+            process::exit(1);
+        }
+    };
+
+    let Some(answer_file) = answer_file else {
+        print!("{}", encoding.to_dimacs());
+        return;
+    };
+    match encoding.board_from_assignment(&read_or_exit(answer_file)) {
+        Ok(board) => println!("solution: {}", board.name_grid()),
+        Err(error) => {
+            println!("invalid answer: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+// Prints the board and pieces as an exact cover matrix or, given an exact cover solver's
+// output, the solutions it lists.
+fn exact_cover(settings: &SolveSettings, output_file: Option<&str>) {
+    let pieces = settings.pieces();
+    let transforms = settings.transforms(&pieces);
+    let supply = settings.supply(&pieces);
+    let matrix = match exact_cover_matrix(&settings.board(), &transforms, &supply) {
+        Ok(matrix) => matrix,
+        Err(error) => {
+            println!("can't encode the board: {}", error);
+            process::exit(1);
+        }
+    };
+
+    let Some(output_file) = output_file else {
+        print!("{}", matrix.to_dlx());
+        return;
+    };
+    match matrix.solutions_from_dlx(&read_or_exit(output_file)) {
+        Ok(solutions) => {
+            for solution in solutions.iter() {
+                println!("solution: {}", solution);
+            }
+            println!("{} solutions", solutions.len());
+        }
+        Err(error) => {
+            println!("invalid solver output: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn read_or_exit(path: &str) -> String {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            println!("couldn't read {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
+// Times both branchings on the 6x10 board. The piece order search's symmetry cut is turned off
+// so both do the same job of listing every solution, reflections and rotations included.
+fn benchmark_branching() {
+    let transforms: Arc<Vec<Vec<Piece>>> = Arc::new(
+        standard_pieces()
+            .iter()
+            .map(Piece::all_transforms)
+            .collect(),
+    );
+    let mut results = vec![];
+
+    for branching in [Branching::PieceOrder, Branching::MostConstrainedCell] {
+        let mut board = create_board(10, 6);
+        board.set_symmetry_cut(false);
+        let start = Instant::now();
+        let result = board.find_solutions(&transforms, branching);
+        let elapsed = start.elapsed();
+        let unique: HashSet<String> = result
+            .solutions
+            .iter()
+            .map(|solution| canonicalise_solution_string(solution))
+            .collect();
+
+        results.push((branching, result.solutions.len(), unique.len(), elapsed));
+    }
+
+    for (branching, solutions, unique, elapsed) in results {
+        println!(
+            "{:?}: {} solutions ({} unique) on 6x10 in {}ms",
+            branching,
+            solutions,
+            unique,
+            elapsed.as_millis()
+        );
+    }
+}
+
+// Times every backend and thread limit on the benchmark problems and writes the results to a
+// file, flagging each run that got worse than the same run in a baseline file.
+fn bench(options: &[String]) {
+    let option = |name: &str| options.iter().find_map(|a| a.strip_prefix(name));
+    let output_file = option("--output=").unwrap_or("bench-results.txt");
+    let tolerance = option("--tolerance=")
+        .and_then(|percent| percent.parse::<f64>().ok())
+        .unwrap_or(20.0)
+        / 100.0;
+    let baseline = match option("--baseline=") {
+        Some(file) => match parse_bench_results(&read_or_exit(file)) {
+            Ok(baseline) => baseline,
+            Err(error) => {
+                println!("invalid baseline {}: {}", file, error);
+                process::exit(1);
+            }
+        },
+        None => vec![],
+    };
+    let problems: Vec<BenchProblem> = bench_problems()
+        .into_iter()
+        .filter(|problem| {
+            option("--problems=").is_none_or(|names| names.split(',').any(|n| n == problem.name))
+        })
+        .collect();
+
+    let mut results = vec![];
+    'problems: for problem in problems.iter() {
+        for backend in Backend::ALL {
+            let thread_limits = match backend.threaded() {
+                true => thread_settings(),
+                false => vec![1],
+            };
+            for threads in thread_limits {
+                let result = run_bench(problem, backend, threads);
+                // A run cut short would look like a regression, so only finished runs are kept
+                // and written.
+                if interrupted() {
+                    break 'problems;
+                }
+                results.extend(result);
+            }
+        }
+    }
+
+    let mut regressed = 0;
+    for result in results.iter() {
+        let note = match baseline.iter().find(|b| b.same_run(result)) {
+            Some(before) => {
+                let found: Vec<String> = regressions(result, before, tolerance)
+                    .iter()
+                    .map(Regression::to_string)
+                    .collect();
+                if found.is_empty() {
+                    String::new()
+                } else {
+                    regressed += 1;
+                    format!(" REGRESSION: {}", found.join("; "))
+                }
+            }
+            None if baseline.is_empty() => String::new(),
+            None => " (not in the baseline)".to_string(),
+        };
+        println!(
+            "{} {}, threads {}: {}ms, {} nodes, {} solutions{}",
+            result.problem,
+            result.backend,
+            match result.threads {
+                0 => "unlimited".to_string(),
+                threads => threads.to_string(),
+            },
+            result.millis,
+            result.nodes,
+            result.solutions,
+            note
+        );
+    }
+
+    let lines: Vec<String> = results.iter().map(BenchResult::to_string).collect();
+    let contents = format!("{}\n{}\n", BENCH_HEADER, lines.join("\n"));
+    if let Err(error) = fs::write(output_file, contents) {
+        println!("couldn't write {}: {}", output_file, error);
+        process::exit(1);
+    }
+    println!("wrote {} results to {}", results.len(), output_file);
+
+    // An interrupted run exits as incomplete once back in main, whatever has regressed.
+    if regressed > 0 && !interrupted() {
+        println!("{} of {} runs regressed", regressed, results.len());
+        process::exit(1);
+    }
+}
+
+// A board with its published count of distinct solutions using the 12 pentominoes, where
+// rotations and reflections of a solution aren't counted again.
+struct KnownCount {
+    width: usize,
+    height: usize,
+    holes: &'static [(usize, usize)],
+    unique: usize,
+}
+
+const KNOWN_COUNTS: [KnownCount; 5] = [
+    KnownCount {
+        width: 10,
+        height: 6,
+        holes: &[],
+        unique: 2339,
+    },
+    KnownCount {
+        width: 12,
+        height: 5,
+        holes: &[],
+        unique: 1010,
+    },
+    KnownCount {
+        width: 15,
+        height: 4,
+        holes: &[],
+        unique: 368,
+    },
+    KnownCount {
+        width: 20,
+        height: 3,
+        holes: &[],
+        unique: 2,
+    },
+    KnownCount {
+        width: 8,
+        height: 8,
+        holes: &[(3, 3), (3, 4), (4, 3), (4, 4)],
+        unique: 65,
+    },
+];
+
+// Solves each board with known counts the way the other options ask for, reporting whether the
+// counts match, and exits with a failure status when any don't.
+fn self_test(settings: &SolveSettings) {
+    let transforms: Arc<Vec<Vec<Piece>>> = Arc::new(
+        standard_pieces()
+            .iter()
+            .map(Piece::all_transforms)
+            .collect(),
+    );
+    let mut failures = 0;
+
+    for known in KNOWN_COUNTS.iter() {
+        let mut board = create_board_with_holes(known.width, known.height, known.holes)
+            .expect("the known boards' holes are on the board");
+        let square = known.width == known.height;
+        let start = Instant::now();
+
+        let (unique, complete) = match settings.count_with_table_mb {
+            Some(table_mb) => {
+                let supply = vec![Supply::Exactly(1); transforms.len()];
+                let count = board.count_solutions(&transforms, &supply, table_mb << 20);
+                let symmetries = if square { 8 } else { 4 };
+                (count.count as usize / symmetries, count.complete)
+            }
+            None => {
+                let result = board.find_solutions(&transforms, settings.branching);
+                let distinct: HashSet<String> = result
+                    .solutions
+                    .iter()
+                    .map(|solution| match square {
+                        true => canonicalise_square_solution_string(solution),
+                        false => canonicalise_solution_string(solution),
+                    })
+                    .collect();
+                (distinct.len(), result.complete)
+            }
+        };
+
+        let passed = unique == known.unique;
+        if complete && !passed {
+            failures += 1;
+        }
+        let holes = match known.holes.len() {
+            0 => String::new(),
+            n => format!(" with {} cells left out", n),
+        };
+        println!(
+            "{} {}x{}{}: {} unique solutions, expected {}, in {}ms",
+            match (complete, passed) {
+                (false, _) => "incomplete",
+                (true, true) => "pass",
+                (true, false) => "FAIL",
+            },
+            known.height,
+            known.width,
+            holes,
+            unique,
+            known.unique,
+            start.elapsed().as_millis()
+        );
     }
+
+    // An interrupted run is reported as incomplete rather than failed.
+    if interrupted() {
+        return;
+    }
+    if failures > 0 {
+        println!("{} of {} self tests failed", failures, KNOWN_COUNTS.len());
+        process::exit(1);
+    }
+    println!("all {} self tests passed", KNOWN_COUNTS.len());
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::canonicalise_solution_string;
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    stop_on_ctrl_c();
+
+    match args.get(1).map(String::as_str) {
+        Some("verify") => verify(&args[2..]),
+        Some("benchmark-branching") => benchmark_branching(),
+        Some("bench") => bench(&args[2..]),
+        Some("selftest") => self_test(&SolveSettings::from_args(&args[2..])),
+        Some("clues") if args.len() > 2 => solve_clues(&args[2]),
+        Some("box") if args.len() > 2 => {
+            solve_box(&args[2], args[3..].iter().any(|a| a == "--pentacubes"))
+        }
+        Some("cnf") => sat(&SolveSettings::from_args(&args[2..]), None),
+        Some("from-sat") if args.len() > 2 => {
+            sat(&SolveSettings::from_args(&args[3..]), Some(&args[2]))
+        }
+        Some("dlx") => exact_cover(&SolveSettings::from_args(&args[2..]), None),
+        Some("from-dlx") if args.len() > 2 => {
+            exact_cover(&SolveSettings::from_args(&args[3..]), Some(&args[2]))
+        }
+        Some("strip") if args.len() > 2 => count_strips(&args[2], &args[3..]),
+        Some("pentahexes") if args.len() > 2 => solve_lattice(Lattice::Hexagonal, &args[2]),
+        Some("hexiamonds") if args.len() > 2 => solve_lattice(Lattice::Triangular, &args[2]),
+        _ => {
+            solve(&SolveSettings::from_args(&args[1..]));
+        }
+    }
 
-    #[test]
-    fn generates_expected_canonical_string() {
-        assert_eq!("AB CD", canonicalise_solution_string("AB CD"));
-        assert_eq!("AB CD", canonicalise_solution_string("BA DC"));
-        assert_eq!("AB CD", canonicalise_solution_string("DC BA"));
-        assert_eq!("AB CD", canonicalise_solution_string("CD AB"));
-        assert_eq!("ABC ADE", canonicalise_solution_string("ADE ABC"));
+    // Searches stopped by Ctrl-C have reported what they found by now, which is incomplete.
+    if interrupted() {
+        println!("interrupted: the results above are incomplete");
+        process::exit(130);
     }
 }
@@ -0,0 +1,324 @@
+use crate::board::{create_board, Board, Placement};
+use crate::error::Error;
+use crate::pieces::{PentominoName, Piece};
+
+pub(crate) struct NameGrid {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) cells: Vec<char>,
+}
+
+pub(crate) fn parse_name_grid(name_grid: &str) -> Result<NameGrid, Error> {
+    let mut width = 0;
+    let mut height = 0;
+    let mut cells = vec![];
+
+    for line in name_grid.split_whitespace() {
+        let current_width = line.chars().count();
+
+        if height == 0 {
+            width = current_width;
+        } else if current_width != width {
+            return Err(Error::RaggedRow {
+                row: height,
+                width: current_width,
+                expected: width,
+            });
+        }
+
+        cells.extend(line.chars());
+        height += 1;
+    }
+
+    if height == 0 {
+        return Err(Error::EmptyGrid);
+    }
+
+    Ok(NameGrid {
+        width,
+        height,
+        cells,
+    })
+}
+
+impl NameGrid {
+    fn matches(&self, transform: &Piece, row: usize, column: usize) -> bool {
+        if row + usize::from(transform.height) > self.height
+            || column + usize::from(transform.width) > self.width
+        {
+            return false;
+        }
+
+        let name = transform.name_char();
+        for piece_row in 0..transform.height {
+            for piece_column in 0..transform.width {
+                let index = (row + usize::from(piece_row)) * self.width
+                    + column
+                    + usize::from(piece_column);
+
+                if transform.is_solid(piece_row, piece_column) && self.cells[index] != name {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    // The first unfilled named cell in row-major order has to be covered by the first solid
+    // square of whichever transform fills it, so that is the only position tried per transform.
+    // A cell that no piece covers is kept in `unmatched` along with how many pieces were placed
+    // before it, keeping the one found with the most pieces placed as the likeliest culprit.
+    fn place_named<'a>(
+        &self,
+        board: &mut Board<'a>,
+        transforms: &'a [Vec<Piece>],
+        used: &mut [bool],
+        unmatched: &mut Option<(usize, usize)>,
+    ) -> bool {
+        let index = match (0..self.cells.len()).find(|i| self.cells[*i] != '.' && !board.filled[*i])
+        {
+            Some(index) => index,
+            None => return true,
+        };
+        let row = index / self.width;
+        let column = index % self.width;
+
+        for (piece_index, piece_transforms) in transforms.iter().enumerate() {
+            if used[piece_index] || piece_transforms[0].name_char() != self.cells[index] {
+                continue;
+            }
+
+            for transform in piece_transforms.iter() {
+                let offset = (0..transform.width)
+                    .find(|c| transform.is_solid(0, *c))
+                    .map(usize::from)
+                    .unwrap();
+
+                if offset > column || !self.matches(transform, row, column - offset) {
+                    continue;
+                }
+
+                let placement = Placement {
+                    row,
+                    column: column - offset,
+                    piece: transform,
+                };
+                if !board.try_add(placement) {
+                    continue;
+                }
+                used[piece_index] = true;
+
+                if self.place_named(board, transforms, used, unmatched) {
+                    return true;
+                }
+
+                used[piece_index] = false;
+                board.remove_last();
+            }
+        }
+
+        let depth = board.placements.len();
+        if unmatched.is_none_or(|(deepest, _)| depth > deepest) {
+            *unmatched = Some((depth, index));
+        }
+        false
+    }
+}
+
+/// Rebuilds the placements behind a string produced by `Board::name_grid`. Each group of named
+/// cells is matched against the transforms of a piece with that name, using every piece at most
+/// once; cells marked '.' are left empty.
+pub fn board_from_name_grid<'a>(
+    name_grid: &str,
+    transforms: &'a [Vec<Piece>],
+) -> Result<Board<'a>, Error> {
+    let grid = parse_name_grid(name_grid)?;
+
+    if let Some(name) = grid
+        .cells
+        .iter()
+        .find(|c| **c != '.' && !transforms.iter().any(|t| t[0].name_char() == **c))
+    {
+        return Err(Error::UnknownPiece(*name));
+    }
+
+    let mut board = create_board(grid.width, grid.height);
+    let mut used = vec![false; transforms.len()];
+    let mut unmatched = None;
+    if !grid.place_named(&mut board, transforms, &mut used, &mut unmatched) {
+        let (_, index) = unmatched.unwrap();
+
+        return Err(Error::UnmatchedRegion {
+            name: grid.cells[index],
+            row: index / grid.width,
+            column: index % grid.width,
+        });
+    }
+
+    Ok(board)
+}
+
+/// Creates an empty board the size of a clue grid, where a letter means the cell has to be
+/// covered by the piece with that name and '.' leaves the cell open.
+pub fn board_from_clue_grid<'a>(clue_grid: &str) -> Result<Board<'a>, Error> {
+    let grid = parse_name_grid(clue_grid)?;
+
+    let mut board = create_board(grid.width, grid.height);
+    for (index, cell) in grid.cells.iter().enumerate() {
+        if *cell == '.' {
+            continue;
+        }
+
+        let name = PentominoName::from_char(*cell).ok_or(Error::UnknownPiece(*cell))?;
+        board
+            .constraints
+            .clues
+            .push((name, index / grid.width, index % grid.width));
+    }
+
+    Ok(board)
+}
+
+/// The letters of the pieces used in a name grid, sorted and repeated once per copy, which
+/// identifies the subset of pieces a solution uses.
+pub fn piece_names(name_grid: &str) -> String {
+    let mut names: Vec<char> = name_grid
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '.')
+        .collect();
+    names.sort_unstable();
+
+    names.chunks(5).map(|chunk| chunk[0]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{create_board, Placement};
+    use crate::error::Error;
+    use crate::parse::{board_from_clue_grid, board_from_name_grid, piece_names};
+    use crate::pieces::{piece_from_name, PentominoName, Piece};
+
+    fn transforms(names: &[PentominoName]) -> Vec<Vec<Piece>> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| piece_from_name(i as i32, *name).all_transforms())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_name_grid() {
+        let u_piece = piece_from_name(1, PentominoName::U);
+        let x_piece = piece_from_name(2, PentominoName::X);
+        let mut board = create_board(3, 4);
+        board.try_add(Placement {
+            row: 2,
+            column: 0,
+            piece: &u_piece,
+        });
+        board.try_add(Placement {
+            row: 0,
+            column: 0,
+            piece: &x_piece,
+        });
+        let transforms = transforms(&[PentominoName::U, PentominoName::X]);
+
+        let parsed = board_from_name_grid(&board.name_grid(), &transforms).unwrap();
+
+        assert_eq!(".X. XXX UXU UUU", parsed.name_grid());
+        assert_eq!(board.filled, parsed.filled);
+        assert_eq!(2, parsed.placements.len());
+    }
+
+    #[test]
+    fn works_out_transform_and_position() {
+        let transforms = transforms(&[PentominoName::L]);
+
+        let board = board_from_name_grid("... .L. .L. .L. LL.", &transforms).unwrap();
+
+        assert_eq!(1, board.placements.len());
+        assert_eq!(1, board.placements[0].row);
+        assert_eq!(0, board.placements[0].column);
+        assert_eq!(2, board.placements[0].piece.width);
+        assert_eq!(4, board.placements[0].piece.height);
+    }
+
+    #[test]
+    fn separates_touching_pieces_with_the_same_name() {
+        let transforms = transforms(&[PentominoName::I, PentominoName::I]);
+
+        let board = board_from_name_grid("IIIII IIIII", &transforms).unwrap();
+
+        assert_eq!(2, board.placements.len());
+    }
+
+    #[test]
+    fn lists_pieces_used() {
+        assert_eq!("IUUX", piece_names("IIIII UUXUU UXXXU UUXUU"));
+        assert_eq!("UX", piece_names(".X. XXX UXU UUU"));
+    }
+
+    #[test]
+    fn reads_clues_from_grid() {
+        let board = board_from_clue_grid("F.... ....x").unwrap();
+
+        assert_eq!((5, 2), (board.width, board.height));
+        assert_eq!(
+            vec![(PentominoName::F, 0, 0), (PentominoName::X, 1, 4)],
+            board.constraints.clues
+        );
+        assert_eq!(
+            Some(Error::UnknownPiece('Q')),
+            board_from_clue_grid("Q....").err()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_piece_names() {
+        let transforms = transforms(&[PentominoName::I]);
+
+        assert_eq!(
+            Some(Error::UnknownPiece('X')),
+            board_from_name_grid("IIIII XXXXX", &transforms).err()
+        );
+    }
+
+    #[test]
+    fn rejects_cells_that_do_not_match_a_piece() {
+        let transforms = transforms(&[PentominoName::I]);
+
+        assert_eq!(
+            Some(Error::UnmatchedRegion {
+                name: 'I',
+                row: 0,
+                column: 0
+            }),
+            board_from_name_grid("IIII. I....", &transforms).err()
+        );
+    }
+
+    #[test]
+    fn blames_the_piece_that_does_not_match_rather_than_the_first() {
+        let transforms = transforms(&[PentominoName::I, PentominoName::U]);
+
+        assert_eq!(
+            Some(Error::UnmatchedRegion {
+                name: 'U',
+                row: 1,
+                column: 0
+            }),
+            board_from_name_grid("IIIII UUUUU", &transforms).err()
+        );
+    }
+
+    #[test]
+    fn reads_grids_larger_than_255_cells() {
+        let row = ".".repeat(16);
+        let grid = vec![row; 16].join(" ");
+        let board = board_from_name_grid(&grid, &[]).unwrap();
+
+        assert_eq!((16, 16), (board.width, board.height));
+        assert_eq!(256, board.filled.len());
+    }
+}
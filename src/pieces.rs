@@ -1,16 +1,21 @@
 use std::collections::HashSet;
 
-#[derive(Clone, PartialEq)]
-pub(crate) struct Piece {
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Piece {
     pub(crate) id: i32,
     pub(crate) name: PentominoName,
     pub(crate) height: u8,
     pub(crate) width: u8,
     pub(crate) shape: Vec<bool>,
+    pub(crate) one_sided: bool,
+    pub(crate) mirrored: bool,
+    pub(crate) colours: Vec<bool>,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
-pub(crate) enum PentominoName {
+pub enum PentominoName {
     F,
     I,
     L,
@@ -35,8 +40,18 @@ impl PentominoName {
 
         output.chars().next().unwrap()
     }
+
+    /// The pentomino named by a letter in either case, as mirrored pieces are written in lower
+    /// case.
+    pub fn from_char(name: char) -> Option<PentominoName> {
+        use PentominoName::*;
+
+        [F, I, L, N, P, T, U, V, W, X, Y, Z]
+            .into_iter()
+            .find(|n| n.name_char() == name.to_ascii_uppercase())
+    }
 }
-pub(crate) fn piece_from_name(id: i32, name: PentominoName) -> Piece {
+pub fn piece_from_name(id: i32, name: PentominoName) -> Piece {
     let template = match &name {
         PentominoName::F => vec!["*..", "***", ".*."],
         PentominoName::I => vec!["*****"],
@@ -55,22 +70,61 @@ pub(crate) fn piece_from_name(id: i32, name: PentominoName) -> Piece {
     piece_from_template(id, name, template)
 }
 
-fn piece_from_template(id: i32, name: PentominoName, template: Vec<&str>) -> Piece {
-    let mut height: u8 = 0;
-    let mut width: u8 = 0;
+/// The twelve pentominoes used by the solver, with ids doubling as terminal colours.
+pub fn standard_pieces() -> Vec<Piece> {
+    vec![
+        piece_from_name(94, PentominoName::I),  //  1
+        piece_from_name(208, PentominoName::L), //  2
+        piece_from_name(130, PentominoName::Y), //  3
+        piece_from_name(127, PentominoName::N), //  4
+        piece_from_name(4, PentominoName::V),   //  5
+        piece_from_name(217, PentominoName::P), //  6
+        piece_from_name(11, PentominoName::U),  //  7
+        piece_from_name(6, PentominoName::Z),   //  8
+        piece_from_name(252, PentominoName::F), //  9
+        piece_from_name(28, PentominoName::T),  // 10
+        piece_from_name(10, PentominoName::W),  // 11
+        piece_from_name(1, PentominoName::X),   // 12
+    ]
+}
+
+/// Builds a piece from rows of `*` (solid) and `.` (empty) squares.
+///
+/// # Panics
+///
+/// When the template isn't a valid pentomino; use [`try_piece_from_template`] for templates
+/// that aren't known to be valid.
+pub fn piece_from_template(id: i32, name: PentominoName, template: Vec<&str>) -> Piece {
+    try_piece_from_template(id, name, template).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Builds a piece from rows of `*` (solid) and `.` (empty) squares, or says why the rows
+/// don't describe a pentomino.
+pub fn try_piece_from_template(
+    id: i32,
+    name: PentominoName,
+    template: Vec<&str>,
+) -> Result<Piece, Error> {
+    if template.is_empty() {
+        return Err(Error::EmptyTemplate);
+    }
+    let too_large = || Error::TemplateTooLarge {
+        rows: template.len(),
+        columns: template[0].chars().count(),
+    };
+    let height = u8::try_from(template.len()).map_err(|_| too_large())?;
+    let width = u8::try_from(template[0].chars().count()).map_err(|_| too_large())?;
     let mut shape: Vec<bool> = Vec::new();
 
-    for line in template {
-        height += 1;
-        let current_width = u8::try_from(line.len()).ok().unwrap();
-
-        if width == 0 {
-            width = current_width;
-        } else if current_width != width {
-            panic!(
-                "on line {} got width {} but expected {}",
-                height, current_width, width
-            )
+    for (row, line) in template.iter().enumerate() {
+        let current_width = line.chars().count();
+
+        if current_width != usize::from(width) {
+            return Err(Error::RaggedRow {
+                row,
+                width: current_width,
+                expected: usize::from(width),
+            });
         }
 
         for c in line.chars() {
@@ -80,47 +134,172 @@ fn piece_from_template(id: i32, name: PentominoName, template: Vec<&str>) -> Pie
 
     let square_count = shape.iter().filter(|s| **s).count();
     if square_count != 5 {
-        panic!(
-            "expected five squares but got {} for piece name {}",
-            square_count,
-            name.name_char()
-        );
+        return Err(Error::WrongSquareCount {
+            name: name.name_char(),
+            count: square_count,
+        });
     }
 
-    Piece {
+    Ok(Piece {
         id,
         name,
         height,
         width,
         shape,
+        one_sided: false,
+        mirrored: false,
+        colours: vec![],
+    })
+}
+
+/// A piece that can only be rotated, as used in one-sided pentomino puzzles.
+pub fn one_sided_piece_from_name(id: i32, name: PentominoName) -> Piece {
+    Piece {
+        one_sided: true,
+        ..piece_from_name(id, name)
     }
 }
 
+/// The mirror image of a one-sided piece, which counts as a distinct piece.
+pub fn mirrored_piece_from_name(id: i32, name: PentominoName) -> Piece {
+    Piece {
+        mirrored: true,
+        ..one_sided_piece_from_name(id, name).flip_horizontaly()
+    }
+}
+
+/// A piece coloured like a chessboard, with the top left square of its bounding box dark or
+/// light.
+pub fn checkered_piece_from_name(id: i32, name: PentominoName, top_left_dark: bool) -> Piece {
+    let piece = piece_from_name(id, name);
+    let colours = (0..piece.height)
+        .flat_map(|row| (0..piece.width).map(move |column| (row + column) % 2 == 0))
+        .map(|even| even == top_left_dark)
+        .collect();
+
+    Piece { colours, ..piece }
+}
+
+/// The eighteen one-sided pentominoes: the twelve pieces plus mirror images of the six that
+/// differ from their reflection.
+pub fn one_sided_pieces() -> Vec<Piece> {
+    vec![
+        one_sided_piece_from_name(94, PentominoName::I), //  1
+        one_sided_piece_from_name(208, PentominoName::L), //  2
+        mirrored_piece_from_name(172, PentominoName::L), //  3
+        one_sided_piece_from_name(130, PentominoName::Y), //  4
+        mirrored_piece_from_name(136, PentominoName::Y), //  5
+        one_sided_piece_from_name(127, PentominoName::N), //  6
+        mirrored_piece_from_name(91, PentominoName::N),  //  7
+        one_sided_piece_from_name(4, PentominoName::V),  //  8
+        one_sided_piece_from_name(217, PentominoName::P), //  9
+        mirrored_piece_from_name(161, PentominoName::P), // 10
+        one_sided_piece_from_name(11, PentominoName::U), // 11
+        one_sided_piece_from_name(6, PentominoName::Z),  // 12
+        mirrored_piece_from_name(40, PentominoName::Z),  // 13
+        one_sided_piece_from_name(252, PentominoName::F), // 14
+        mirrored_piece_from_name(214, PentominoName::F), // 15
+        one_sided_piece_from_name(28, PentominoName::T), // 16
+        one_sided_piece_from_name(10, PentominoName::W), // 17
+        one_sided_piece_from_name(1, PentominoName::X),  // 18
+    ]
+}
+
+// A piece's squares, width and height, which together tell its transforms apart.
+type ShapeId = (Vec<bool>, u8, u8);
+
 impl Piece {
-    pub(crate) fn flip_horizontaly(&self) -> Piece {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn name(&self) -> PentominoName {
+        self.name
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Whether each square of the bounding box is part of the piece, row by row.
+    pub fn shape(&self) -> &[bool] {
+        &self.shape
+    }
+
+    /// One-sided pieces can't be flipped, so only their rotations are used.
+    pub fn one_sided(&self) -> bool {
+        self.one_sided
+    }
+
+    /// Marks the mirror image of a one-sided piece, named with a lower case letter.
+    pub fn mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    /// Whether each square is dark, laid out like `shape`, or empty for an uncoloured piece.
+    pub fn colours(&self) -> &[bool] {
+        &self.colours
+    }
+
+    /// The same piece, but only to be rotated.
+    pub fn to_one_sided(&self) -> Piece {
+        Piece {
+            one_sided: true,
+            ..self.clone()
+        }
+    }
+
+    /// The letter used for this piece in name grids, lower case for mirrored one-sided pieces.
+    pub fn name_char(&self) -> char {
+        let name_char = self.name.name_char();
+
+        if self.mirrored {
+            name_char.to_ascii_lowercase()
+        } else {
+            name_char
+        }
+    }
+
+    pub fn flip_horizontaly(&self) -> Piece {
         let mut shape: Vec<bool> = Vec::with_capacity(self.shape.len());
+        let mut colours: Vec<bool> = Vec::with_capacity(self.colours.len());
 
         for r in 0..self.height {
             for c in 0..self.width {
-                let copy_index = usize::from(r * self.width + (self.width - c - 1));
+                let copy_index = self.index(r, self.width - c - 1);
 
                 shape.push(self.shape[copy_index]);
+                if self.is_coloured() {
+                    colours.push(self.colours[copy_index]);
+                }
             }
         }
 
-        Piece { shape, ..*self }
+        Piece {
+            shape,
+            colours,
+            ..*self
+        }
     }
 
-    pub(crate) fn rotate_clockwise(&self) -> Piece {
+    pub fn rotate_clockwise(&self) -> Piece {
         let mut shape: Vec<bool> = Vec::with_capacity(self.shape.len());
+        let mut colours: Vec<bool> = Vec::with_capacity(self.colours.len());
 
         for r in 0..self.width {
             for c in 0..self.height {
                 let from_row = self.height - c - 1;
                 let from_column = r;
-                let copy_index = usize::from(from_row * self.width + from_column);
+                let copy_index = self.index(from_row, from_column);
 
                 shape.push(self.shape[copy_index]);
+                if self.is_coloured() {
+                    colours.push(self.colours[copy_index]);
+                }
             }
         }
 
@@ -128,12 +307,13 @@ impl Piece {
             height: self.width,
             width: self.height,
             shape,
+            colours,
             ..*self
         }
     }
 
     #[cfg(feature = "trace")]
-    pub(crate) fn shape_string(&self) -> String {
+    pub fn shape_string(&self) -> String {
         let mut result = String::new();
 
         for i in 0..self.shape.len() {
@@ -146,52 +326,156 @@ impl Piece {
         result
     }
 
-    fn shape_id(&self) -> (u32, u8, u8) {
-        (
-            self.shape
-                .iter()
-                .fold(0u32, |acc, f| (acc << 1) + if *f { 1 } else { 0 }),
-            self.width,
-            self.height,
-        )
+    // The squares themselves rather than a bitmask of them, as templates can have far more
+    // squares than fit in an integer.
+    fn shape_id(&self) -> ShapeId {
+        (self.shape.clone(), self.width, self.height)
     }
 
-    pub(crate) fn all_transforms(&self) -> Vec<Piece> {
-        let mut result = vec![];
-        let mut existing: HashSet<(u32, u8, u8)> = HashSet::new();
+    // Transforms with the same shape still differ when their colours do.
+    fn transform_id(&self) -> (ShapeId, Vec<bool>) {
+        (self.shape_id(), self.colours.clone())
+    }
 
-        let mut add_all_rotations = |piece: &Piece| {
-            let mut current = piece.clone();
+    fn add_unique_rotations(
+        &self,
+        result: &mut Vec<Piece>,
+        existing: &mut HashSet<(ShapeId, Vec<bool>)>,
+    ) {
+        let mut current = self.clone();
 
-            for i in 0..4 {
-                if i > 0 {
-                    current = current.rotate_clockwise();
-                }
+        for i in 0..4 {
+            if i > 0 {
+                current = current.rotate_clockwise();
+            }
 
-                let shape_id = current.shape_id();
-                if !existing.contains(&shape_id) {
-                    existing.insert(shape_id);
-                    result.push(current.clone());
-                }
+            let transform_id = current.transform_id();
+            if !existing.contains(&transform_id) {
+                existing.insert(transform_id);
+                result.push(current.clone());
             }
-        };
+        }
+    }
+
+    /// The distinct rotations of this piece, without reflections.
+    pub fn rotations(&self) -> Vec<Piece> {
+        let mut result = vec![];
+        self.add_unique_rotations(&mut result, &mut HashSet::new());
+
+        result
+    }
 
-        add_all_rotations(self);
+    /// The distinct rotations and, unless the piece is one-sided, reflections of this piece.
+    pub fn all_transforms(&self) -> Vec<Piece> {
+        let mut result = vec![];
+        let mut existing = HashSet::new();
+
+        self.add_unique_rotations(&mut result, &mut existing);
 
-        let flipped = self.flip_horizontaly();
-        add_all_rotations(&flipped);
+        if !self.one_sided {
+            let flipped = self.flip_horizontaly();
+            flipped.add_unique_rotations(&mut result, &mut existing);
+        }
 
         result
     }
 
-    pub(crate) fn is_solid(&self, row: u8, column: u8) -> bool {
-        self.shape[usize::from(row * self.width + column)]
+    // Where a square is in `shape` and `colours`, worked out in usize as a template up to 255
+    // squares across has too many squares to count in u8.
+    fn index(&self, row: u8, column: u8) -> usize {
+        usize::from(row) * usize::from(self.width) + usize::from(column)
+    }
+
+    pub fn is_solid(&self, row: u8, column: u8) -> bool {
+        self.shape[self.index(row, column)]
+    }
+
+    pub fn is_coloured(&self) -> bool {
+        !self.colours.is_empty()
+    }
+
+    pub fn is_dark(&self, row: u8, column: u8) -> bool {
+        self.colours[self.index(row, column)]
+    }
+
+    // How many dark cells the piece can cover: fixed for a coloured piece, and for an
+    // uncoloured one either way round on a checkerboard or anything at all otherwise.
+    pub(crate) fn dark_cell_counts(&self, checkerboard: bool) -> Vec<usize> {
+        let cells = (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |column| (row, column)))
+            .filter(|(row, column)| self.is_solid(*row, *column));
+
+        if self.is_coloured() {
+            vec![cells
+                .filter(|(row, column)| self.is_dark(*row, *column))
+                .count()]
+        } else if checkerboard {
+            let even = cells.filter(|(row, column)| row % 2 == column % 2).count();
+            vec![even, 5 - even]
+        } else {
+            (0..=5).collect()
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::pieces::{piece_from_name, PentominoName};
+    use crate::error::Error;
+    use crate::pieces::{
+        checkered_piece_from_name, mirrored_piece_from_name, one_sided_piece_from_name,
+        one_sided_pieces, piece_from_name, try_piece_from_template, PentominoName, Piece,
+    };
+
+    #[test]
+    fn reports_invalid_templates() {
+        assert_eq!(
+            Err(Error::RaggedRow {
+                row: 1,
+                width: 2,
+                expected: 3
+            }),
+            try_piece_from_template(1, PentominoName::U, vec!["*.*", "**"])
+        );
+        // Counted from 0 like the rows of name grids and boards.
+        assert_eq!(
+            "row 1 has width 2 but expected 3",
+            try_piece_from_template(1, PentominoName::U, vec!["*.*", "**"])
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            Err(Error::WrongSquareCount {
+                name: 'I',
+                count: 4
+            }),
+            try_piece_from_template(1, PentominoName::I, vec!["****"])
+        );
+        assert_eq!(
+            Err(Error::EmptyTemplate),
+            try_piece_from_template(1, PentominoName::I, vec![])
+        );
+        assert_eq!(
+            Err(Error::TemplateTooLarge {
+                rows: 1,
+                columns: 300
+            }),
+            try_piece_from_template(1, PentominoName::I, vec![&"*".repeat(300)])
+        );
+    }
+
+    #[test]
+    fn templates_larger_than_255_squares_can_be_turned() {
+        let mut template = vec![".".repeat(20); 20];
+        template[19] = format!("{}*****", ".".repeat(15));
+        let rows = template.iter().map(String::as_str).collect();
+        let piece = try_piece_from_template(1, PentominoName::I, rows).unwrap();
+
+        assert!(piece.is_solid(19, 19));
+        assert!(piece.flip_horizontaly().is_solid(19, 0));
+        assert!(piece.rotate_clockwise().is_solid(19, 0));
+        assert_eq!(8, piece.all_transforms().len());
+        assert_eq!(vec![3, 2], piece.dark_cell_counts(true));
+    }
 
     #[test]
     fn can_create_shape_from_template() {
@@ -229,6 +513,23 @@ mod tests {
         assert_eq!(vec![true, true, true, false, true, true], rotated.shape);
     }
 
+    #[test]
+    fn colours_follow_squares_when_rotating_and_flipping() {
+        let input = checkered_piece_from_name(123, PentominoName::U, true);
+
+        assert_eq!(vec![true, false, true, false, true, false], input.colours);
+        assert_eq!(
+            vec![false, true, true, false, false, true],
+            input.rotate_clockwise().colours
+        );
+        assert_eq!(input.colours, input.flip_horizontaly().colours);
+        assert_eq!(vec![3], input.dark_cell_counts(true));
+        assert_eq!(
+            vec![3, 2],
+            piece_from_name(1, PentominoName::U).dark_cell_counts(true)
+        );
+    }
+
     #[cfg(feature = "trace")]
     #[test]
     fn can_convert_to_shape_string() {
@@ -241,7 +542,10 @@ mod tests {
     fn can_calculate_shape_id() {
         let input = piece_from_name(123, PentominoName::U);
 
-        assert_eq!((0x2f, 3, 2), input.shape_id())
+        assert_eq!(
+            (vec![true, false, true, true, true, true], 3, 2),
+            input.shape_id()
+        )
     }
 
     #[test]
@@ -263,6 +567,48 @@ mod tests {
         assert_eq!(8, transforms.len());
     }
 
+    #[test]
+    fn one_sided_pieces_are_only_rotated() {
+        let input = one_sided_piece_from_name(123, PentominoName::F);
+
+        assert_eq!(4, input.all_transforms().len());
+        assert_eq!(input.rotations(), input.all_transforms());
+    }
+
+    #[test]
+    fn mirrored_pieces_have_lower_case_names() {
+        let input = mirrored_piece_from_name(123, PentominoName::F);
+
+        assert_eq!('f', input.name_char());
+        assert_eq!(
+            'F',
+            one_sided_piece_from_name(123, PentominoName::F).name_char()
+        );
+        assert_eq!(
+            input.shape,
+            piece_from_name(1, PentominoName::F)
+                .flip_horizontaly()
+                .shape
+        );
+    }
+
+    #[test]
+    fn one_sided_set_has_eighteen_distinct_pieces() {
+        let pieces = one_sided_pieces();
+        let mut shapes: Vec<_> = pieces
+            .iter()
+            .flat_map(Piece::all_transforms)
+            .map(|p| p.shape_id())
+            .collect();
+        let transform_count = shapes.len();
+        shapes.sort_unstable();
+        shapes.dedup();
+
+        assert_eq!(18, pieces.len());
+        assert_eq!(transform_count, shapes.len());
+        assert_eq!(63, transform_count);
+    }
+
     #[test]
     fn can_test_solidity() {
         let input = piece_from_name(123, PentominoName::P);
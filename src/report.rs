@@ -0,0 +1,42 @@
+//! Hearing about a search while it runs. The library never prints; callers that want to show
+//! solutions or progress as they come give the board a [`Reporter`].
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Something that happened during a search.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchEvent<'a> {
+    /// A solution as a name grid, reported as soon as it is found.
+    Solution(&'a str),
+    /// How many of the top-level subtrees have been searched so far.
+    Progress { done: usize, total: usize },
+}
+
+/// A callback for search events, shared by every search thread so it may be called from any
+/// of them.
+#[derive(Clone)]
+pub struct Reporter(Arc<dyn Fn(&SearchEvent) + Send + Sync>);
+
+impl Reporter {
+    pub fn new(callback: impl Fn(&SearchEvent) + Send + Sync + 'static) -> Reporter {
+        Reporter(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, event: &SearchEvent) {
+        (self.0)(event)
+    }
+}
+
+// Reports to the reporter, if there is one.
+pub(crate) fn report(reporter: &Option<Reporter>, event: &SearchEvent) {
+    if let Some(reporter) = reporter {
+        reporter.report(event);
+    }
+}
+
+impl fmt::Debug for Reporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Reporter")
+    }
+}
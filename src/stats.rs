@@ -0,0 +1,128 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Counters gathered while searching, merged across the search threads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchStatistics {
+    /// Board states reached, indexed by the number of pieces placed.
+    pub nodes_per_depth: Vec<u64>,
+    pub placements_tried: u64,
+    pub placements_rejected: u64,
+    /// Board states abandoned because an empty region could not be filled.
+    pub prunes: u64,
+    /// Solutions found below each top-level placement, in the order the threads were started.
+    pub solutions_per_subtree: Vec<usize>,
+    pub thread_times: Vec<Duration>,
+}
+
+impl SearchStatistics {
+    pub(crate) fn record_node(&mut self, depth: usize) {
+        if self.nodes_per_depth.len() <= depth {
+            self.nodes_per_depth.resize(depth + 1, 0);
+        }
+        self.nodes_per_depth[depth] += 1;
+    }
+
+    pub(crate) fn record_placement(&mut self, added: bool) {
+        self.placements_tried += 1;
+        if !added {
+            self.placements_rejected += 1;
+        }
+    }
+
+    pub(crate) fn record_prune(&mut self) {
+        self.prunes += 1;
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes_per_depth.iter().sum()
+    }
+
+    pub fn merge(&mut self, other: &SearchStatistics) {
+        for (depth, nodes) in other.nodes_per_depth.iter().enumerate() {
+            if self.nodes_per_depth.len() <= depth {
+                self.nodes_per_depth.resize(depth + 1, 0);
+            }
+            self.nodes_per_depth[depth] += nodes;
+        }
+        self.placements_tried += other.placements_tried;
+        self.placements_rejected += other.placements_rejected;
+        self.prunes += other.prunes;
+        self.solutions_per_subtree
+            .extend_from_slice(&other.solutions_per_subtree);
+        self.thread_times.extend_from_slice(&other.thread_times);
+    }
+}
+
+impl fmt::Display for SearchStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "nodes visited: {}", self.nodes())?;
+        for (depth, nodes) in self.nodes_per_depth.iter().enumerate() {
+            writeln!(f, "  depth {:>2}: {}", depth, nodes)?;
+        }
+        writeln!(
+            f,
+            "placements tried: {} ({} rejected)",
+            self.placements_tried, self.placements_rejected
+        )?;
+        writeln!(f, "prunes: {}", self.prunes)?;
+        writeln!(f, "solutions per subtree: {:?}", self.solutions_per_subtree)?;
+        let thread_millis: Vec<u128> = self.thread_times.iter().map(|t| t.as_millis()).collect();
+        write!(f, "thread times (ms): {:?}", thread_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::SearchStatistics;
+    use std::time::Duration;
+
+    #[test]
+    fn records_nodes_by_depth() {
+        let mut statistics = SearchStatistics::default();
+        statistics.record_node(0);
+        statistics.record_node(2);
+        statistics.record_node(2);
+
+        assert_eq!(vec![1, 0, 2], statistics.nodes_per_depth);
+        assert_eq!(3, statistics.nodes());
+    }
+
+    #[test]
+    fn counts_rejected_placements() {
+        let mut statistics = SearchStatistics::default();
+        statistics.record_placement(true);
+        statistics.record_placement(false);
+
+        assert_eq!(2, statistics.placements_tried);
+        assert_eq!(1, statistics.placements_rejected);
+    }
+
+    #[test]
+    fn merges_thread_statistics() {
+        let mut first = SearchStatistics {
+            nodes_per_depth: vec![1],
+            prunes: 2,
+            solutions_per_subtree: vec![3],
+            thread_times: vec![Duration::from_millis(4)],
+            ..Default::default()
+        };
+        let second = SearchStatistics {
+            nodes_per_depth: vec![0, 5],
+            prunes: 6,
+            solutions_per_subtree: vec![7],
+            thread_times: vec![Duration::from_millis(8)],
+            ..Default::default()
+        };
+
+        first.merge(&second);
+
+        assert_eq!(vec![1, 5], first.nodes_per_depth);
+        assert_eq!(8, first.prunes);
+        assert_eq!(vec![3, 7], first.solutions_per_subtree);
+        assert_eq!(
+            vec![Duration::from_millis(4), Duration::from_millis(8)],
+            first.thread_times
+        );
+    }
+}
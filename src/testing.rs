@@ -0,0 +1,27 @@
+//! Fixtures shared by the tests of several modules.
+
+use std::sync::Arc;
+
+use crate::board::Placement;
+use crate::pieces::{piece_from_name, PentominoName, Piece};
+
+/// Two U pieces, an X and an I, which fill a 5x4 board.
+pub(crate) fn u_u_x_i_pieces() -> Vec<Piece> {
+    vec![
+        piece_from_name(1, PentominoName::U),
+        piece_from_name(2, PentominoName::U),
+        piece_from_name(3, PentominoName::X),
+        piece_from_name(4, PentominoName::I),
+    ]
+}
+
+/// The transforms of `u_u_x_i_pieces`, ready to search with.
+pub(crate) fn u_u_x_i_transforms() -> Arc<Vec<Vec<Piece>>> {
+    Arc::new(u_u_x_i_pieces().iter().map(Piece::all_transforms).collect())
+}
+
+/// Whether two placements put the same transform in the same place. Transforms are compared
+/// by address, so both have to come from the same piece set.
+pub(crate) fn same_placement(a: &Placement, b: &Placement) -> bool {
+    (a.row, a.column) == (b.row, b.column) && std::ptr::eq(a.piece, b.piece)
+}
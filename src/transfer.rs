@@ -0,0 +1,201 @@
+//! Counting tilings of long strips without listing them, by sweeping the strip one cell at a
+//! time and only remembering the cells just ahead that earlier pieces already cover.
+
+use std::collections::HashMap;
+
+use crate::bigint::BigUint;
+use crate::board::Supply;
+use crate::error::Error;
+use crate::pieces::Piece;
+
+// An orientation as the cells it covers ahead of its first cell in column order, with how far
+// above and below that cell's row it reaches.
+struct StripOrientation {
+    group: usize,
+    mask: u32,
+    rows_above: usize,
+    rows_below: usize,
+}
+
+fn strip_orientations(height: usize, transforms: &[Vec<Piece>]) -> Vec<StripOrientation> {
+    let mut orientations = vec![];
+
+    for (group, group_transforms) in transforms.iter().enumerate() {
+        for transform in group_transforms {
+            let mut cells: Vec<(usize, usize)> = (0..transform.width)
+                .flat_map(|column| (0..transform.height).map(move |row| (column, row)))
+                .filter(|(column, row)| transform.is_solid(*row, *column))
+                .map(|(column, row)| (usize::from(column), usize::from(row)))
+                .collect();
+            cells.sort_unstable();
+
+            let first_row = cells[0].1;
+            let top = cells.iter().map(|(_, row)| *row).min().unwrap();
+            let bottom = cells.iter().map(|(_, row)| *row).max().unwrap();
+            if bottom - top >= height {
+                continue;
+            }
+
+            orientations.push(StripOrientation {
+                group,
+                mask: cells
+                    .iter()
+                    .map(|(column, row)| 1 << (column * height + row - first_row))
+                    .fold(0, |mask, bit| mask | bit),
+                rows_above: first_row - top,
+                rows_below: bottom - first_row,
+            });
+        }
+    }
+
+    orientations
+}
+
+/// Counts the ways to tile strips `height` cells tall, returning the count for every length
+/// from 0 up to `max_length`. Rotations and reflections of a tiling count separately. The
+/// strip is swept a cell at a time down each column, keeping only which of the next few cells
+/// are covered and, when pieces can't repeat, which pieces are used, so long strips only cost
+/// time in proportion to their length. Each piece can be used at most once, exactly once or
+/// without limit; larger counts aren't supported.
+pub fn count_strip_tilings(
+    height: usize,
+    max_length: usize,
+    transforms: &[Vec<Piece>],
+    supply: &[Supply],
+) -> Result<Vec<BigUint>, Error> {
+    // Pieces reach at most five columns ahead, which has to fit in the window of covered cells,
+    // and each piece needs a bit of its own to mark it used.
+    if height == 0 || 5 * height > 32 || transforms.len() > 32 {
+        return Err(Error::UnsupportedStrip {
+            height,
+            pieces: transforms.len(),
+        });
+    }
+    if let Some(unsupported) = supply
+        .iter()
+        .find(|s| matches!(s, Supply::Exactly(n) | Supply::AtMost(n) if *n > 1))
+    {
+        return Err(Error::UnsupportedSupply(*unsupported));
+    }
+    let group_bits = |wanted: fn(&Supply) -> bool| {
+        (0..supply.len())
+            .filter(|group| wanted(&supply[*group]))
+            .fold(0u32, |bits, group| bits | 1 << group)
+    };
+    let limited = group_bits(|s| *s != Supply::Unlimited);
+    let required = group_bits(Supply::required);
+
+    let orientations = strip_orientations(height, transforms);
+    let mut states: HashMap<(u32, u32), BigUint> = HashMap::new();
+    states.insert((0, 0), BigUint::from(1));
+    let mut counts = vec![];
+
+    for position in 0..=max_length * height {
+        let row = position % height;
+        if row == 0 {
+            let mut complete = BigUint::zero();
+            for (_, ways) in states
+                .iter()
+                .filter(|((covered, used), _)| *covered == 0 && used & required == required)
+            {
+                complete.add(ways);
+            }
+            counts.push(complete);
+        }
+        if position == max_length * height {
+            break;
+        }
+
+        let mut next: HashMap<(u32, u32), BigUint> = HashMap::new();
+        for ((covered, used), ways) in states {
+            if covered & 1 == 1 {
+                next.entry((covered >> 1, used)).or_default().add(&ways);
+                continue;
+            }
+
+            for orientation in orientations.iter() {
+                let bit = 1 << orientation.group;
+                if !supply[orientation.group].available()
+                    || used & limited & bit != 0
+                    || orientation.rows_above > row
+                    || row + orientation.rows_below >= height
+                    || covered & orientation.mask != 0
+                {
+                    continue;
+                }
+
+                next.entry(((covered | orientation.mask) >> 1, used | (bit & limited)))
+                    .or_default()
+                    .add(&ways);
+            }
+        }
+        states = next;
+    }
+
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bigint::BigUint;
+    use crate::board::Supply;
+    use crate::error::Error;
+    use crate::pieces::{piece_from_name, standard_pieces, PentominoName, Piece};
+    use crate::transfer::count_strip_tilings;
+
+    #[test]
+    fn counts_each_piece_once_strips() {
+        let transforms: Vec<Vec<Piece>> = standard_pieces()
+            .iter()
+            .map(Piece::all_transforms)
+            .collect();
+
+        let supply = vec![Supply::AtMost(1); transforms.len()];
+        let counts = count_strip_tilings(3, 20, &transforms, &supply).unwrap();
+        assert_eq!(Some(8), counts[20].to_u64());
+        assert_eq!(Some(1), counts[0].to_u64());
+
+        let counts = count_strip_tilings(4, 15, &transforms, &supply).unwrap();
+        assert_eq!(Some(1472), counts[15].to_u64());
+    }
+
+    #[test]
+    fn counts_unlimited_strips_past_u64() {
+        let transforms = vec![piece_from_name(1, PentominoName::I).all_transforms()];
+        let counts = count_strip_tilings(5, 300, &transforms, &[Supply::Unlimited]).unwrap();
+
+        // Either the first column is a vertical piece, or five pieces lie across columns 1 to 5
+        let mut expected: Vec<BigUint> = vec![BigUint::from(1); 5];
+        for length in 5..=300 {
+            let mut ways = expected[length - 1].clone();
+            ways.add(&expected[length - 5]);
+            expected.push(ways);
+        }
+
+        assert_eq!(expected, counts);
+        assert_eq!(None, counts[300].to_u64());
+    }
+
+    #[test]
+    fn required_pieces_are_in_every_strip_counted() {
+        let transforms = vec![
+            piece_from_name(1, PentominoName::I).all_transforms(),
+            piece_from_name(2, PentominoName::I).all_transforms(),
+        ];
+        let count = |supply: &[Supply]| {
+            let counts = count_strip_tilings(1, 10, &transforms, supply).unwrap();
+            [counts[5].to_u64(), counts[10].to_u64()]
+        };
+
+        assert_eq!([Some(2), Some(2)], count(&[Supply::AtMost(1); 2]));
+        assert_eq!([Some(0), Some(2)], count(&[Supply::Exactly(1); 2]));
+        assert_eq!(
+            [Some(1), Some(0)],
+            count(&[Supply::Exactly(1), Supply::Exactly(0)])
+        );
+        assert_eq!(
+            Some(Error::UnsupportedSupply(Supply::AtMost(2))),
+            count_strip_tilings(1, 10, &transforms, &[Supply::AtMost(2); 2]).err()
+        );
+    }
+}
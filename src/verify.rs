@@ -0,0 +1,149 @@
+use crate::error::Error;
+use crate::parse::{board_from_name_grid, parse_name_grid};
+use crate::pieces::Piece;
+
+/// Checks that a name grid covers the whole board, uses every piece exactly once and that each
+/// piece's cells match one of its transforms.
+pub fn verify_solution(name_grid: &str, pieces: &[Piece]) -> Result<(), Error> {
+    let grid = parse_name_grid(name_grid)?;
+
+    if let Some(index) = grid.cells.iter().position(|c| *c == '.') {
+        return Err(Error::UncoveredCell {
+            row: index / grid.width,
+            column: index % grid.width,
+        });
+    }
+
+    for piece in pieces {
+        let name = piece.name_char();
+        let expected = 5 * pieces.iter().filter(|p| p.name_char() == name).count();
+        let actual = grid.cells.iter().filter(|c| **c == name).count();
+
+        if actual != expected {
+            return Err(Error::WrongCellCount {
+                name,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    let transforms: Vec<Vec<Piece>> = pieces.iter().map(Piece::all_transforms).collect();
+    board_from_name_grid(name_grid, &transforms)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{create_board, Branching};
+    use crate::error::Error;
+    use crate::pieces::{piece_from_name, PentominoName, Piece};
+    use crate::testing::{u_u_x_i_pieces, u_u_x_i_transforms};
+    use crate::verify::verify_solution;
+
+    fn pieces(names: &[PentominoName]) -> Vec<Piece> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| piece_from_name(i as i32, *name))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_solution() {
+        assert_eq!(
+            Ok(()),
+            verify_solution("IIIII UUXUU UXXXU UUXUU", &u_u_x_i_pieces())
+        );
+    }
+
+    #[test]
+    fn accepts_solutions_from_solver() {
+        let transforms = u_u_x_i_transforms();
+        let mut board = create_board(5, 4);
+
+        for solution in board
+            .find_solutions(&transforms, Branching::PieceOrder)
+            .solutions
+        {
+            assert_eq!(Ok(()), verify_solution(&solution, &u_u_x_i_pieces()));
+        }
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        assert_eq!(
+            Err(Error::RaggedRow {
+                row: 1,
+                width: 3,
+                expected: 2
+            }),
+            verify_solution("UU UUU", &pieces(&[PentominoName::U]))
+        );
+    }
+
+    #[test]
+    fn rejects_uncovered_cells() {
+        assert_eq!(
+            Err(Error::UncoveredCell { row: 0, column: 0 }),
+            verify_solution(
+                ".X. XXX UXU UUU",
+                &pieces(&[PentominoName::U, PentominoName::X])
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_pieces() {
+        assert_eq!(
+            Err(Error::UnknownPiece('I')),
+            verify_solution(
+                "IIIII UUXUU UXXXU UUXUU",
+                &pieces(&[PentominoName::U, PentominoName::U, PentominoName::X])
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_unused_pieces() {
+        assert_eq!(
+            Err(Error::WrongCellCount {
+                name: 'L',
+                expected: 5,
+                actual: 0
+            }),
+            verify_solution(
+                "IIIII UUXUU UXXXU UUXUU",
+                &[u_u_x_i_pieces(), vec![piece_from_name(5, PentominoName::L)]].concat()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_pieces_used_more_than_once() {
+        assert_eq!(
+            Err(Error::WrongCellCount {
+                name: 'U',
+                expected: 5,
+                actual: 10
+            }),
+            verify_solution(
+                "IIIII UUXUU UXXXU UUXUU",
+                &pieces(&[PentominoName::U, PentominoName::X, PentominoName::I])
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_regions_not_matching_piece_shape() {
+        assert_eq!(
+            Err(Error::UnmatchedRegion {
+                name: 'U',
+                row: 0,
+                column: 0
+            }),
+            verify_solution("UUUUU", &pieces(&[PentominoName::U]))
+        );
+    }
+}